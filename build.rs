@@ -0,0 +1,17 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "header")]
+    header();
+}
+
+// The committed header is only rewritten on request, so ordinary builds leave the tree alone.
+#[cfg(feature = "header")]
+fn header() {
+    use std::{env, path::PathBuf};
+
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let bindings = cbindgen::generate(&crate_dir).expect("Error generating C header");
+    bindings.write_to_file(crate_dir.join("include/corsola.h"));
+}
@@ -25,12 +25,23 @@ struct App {
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        self.surface = Some(
-            new_surface_ex(event_loop, "Hello Android", 1280.0, 720.0, |attrs| {
-                attrs.with_resizable(true)
-            })
-            .unwrap(),
-        );
+        match &mut self.surface {
+            Some(surf) => surf.attach(event_loop).unwrap(),
+            None => {
+                self.surface = Some(
+                    new_surface_ex(event_loop, "Hello Android", 1280.0, 720.0, |attrs| {
+                        attrs.with_resizable(true)
+                    })
+                    .unwrap(),
+                )
+            }
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(surf) = &mut self.surface {
+            surf.detach();
+        }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
@@ -1,8 +1,10 @@
 use corsola::{
     anyhow::Result,
     glyphon::TextBounds,
+    image,
     new_surface,
     tiny_skia::Pixmap,
+    vfs::Vfs,
     // new_window,
     winit::{
         application::ApplicationHandler,
@@ -27,12 +29,24 @@ struct App {
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        self.surface = Some(new_surface(event_loop, "Hello, world!", 1280.0, 720.0).unwrap());
+        match &mut self.surface {
+            Some(surf) => surf.attach(event_loop).unwrap(),
+            None => {
+                self.surface =
+                    Some(new_surface(event_loop, "Hello, world!", 1280.0, 720.0).unwrap())
+            }
+        }
         // self.surface = Some(Surface::new(self.window.as_ref().unwrap()).unwrap());
         // self.window = Some(new_window(event_loop, "Hello, world!", 1280.0, 720.0).unwrap());
         // self.renderer = Some(Renderer::new(self.window.as_ref().unwrap()).unwrap());
     }
 
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(surf) = &mut self.surface {
+            surf.detach();
+        }
+    }
+
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => {
@@ -85,9 +99,17 @@ fn main() -> Result<()> {
     event_loop.set_control_flow(ControlFlow::Poll);
 
     let mut app = App::default();
+    // embedded, so the example runs from any directory
+    Vfs::global().mount_embedded(
+        "examples",
+        [(
+            "undermine_cloth.png",
+            &include_bytes!("undermine_cloth.png")[..],
+        )],
+    );
     app.textures.insert(
         "background".to_owned(),
-        Pixmap::load_png("examples/undermine_cloth.png")?,
+        image::load("examples/undermine_cloth.png")?,
     );
     event_loop.run_app(&mut app)?;
     Ok(())
@@ -1,13 +1,15 @@
 use corsola::{
     anyhow::Result,
-    new_surface, new_surface_ex,
+    image,
+    new_surface, new_surface_with_fonts_ex,
     tiny_skia::Pixmap,
+    vfs::Vfs,
     winit::{
         application::ApplicationHandler,
         event::{ElementState, KeyEvent, WindowEvent},
         event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
         raw_window_handle::HasRawWindowHandle,
-        window::{Window, WindowId},
+        window::WindowId,
     },
     Surface, TextParams,
 };
@@ -24,7 +26,7 @@ struct App {
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         let surface = new_surface(event_loop, "Hello, world!", 1280.0, 720.0).unwrap();
-        let window_id = surface.window().id();
+        let window_id = surface.window().unwrap().id();
         self.parent = Some(window_id);
         self.surfaces.insert(window_id, surface);
     }
@@ -65,9 +67,9 @@ impl ApplicationHandler for App {
                 ..
             } => {
                 if let Some(parent_id) = &self.parent {
-                    let parent = self.surfaces.get(parent_id).unwrap().window();
+                    let parent = self.surfaces.get(parent_id).unwrap();
                     let child = spawn_child(parent, event_loop).unwrap();
-                    let child_id = child.window().id();
+                    let child_id = child.window().unwrap().id();
                     self.surfaces.insert(child_id, child);
                 }
             }
@@ -76,11 +78,17 @@ impl ApplicationHandler for App {
     }
 }
 
-fn spawn_child(parent: &Window, event_loop: &ActiveEventLoop) -> Result<Surface> {
-    let parent = parent.raw_window_handle()?;
-    new_surface_ex(event_loop, "Child Window", 100.0, 100.0, |attrs| unsafe {
-        attrs.with_parent_window(Some(parent))
-    })
+fn spawn_child(parent: &Surface, event_loop: &ActiveEventLoop) -> Result<Surface> {
+    let fonts = parent.fonts();
+    let parent = parent.window().unwrap().raw_window_handle()?;
+    Ok(new_surface_with_fonts_ex(
+        event_loop,
+        "Child Window",
+        100.0,
+        100.0,
+        &fonts,
+        |attrs| unsafe { attrs.with_parent_window(Some(parent)) },
+    )?)
 }
 
 fn main() -> Result<()> {
@@ -88,9 +96,17 @@ fn main() -> Result<()> {
     event_loop.set_control_flow(ControlFlow::Poll);
 
     let mut app = App::default();
+    // embedded, so the example runs from any directory
+    Vfs::global().mount_embedded(
+        "examples",
+        [(
+            "undermine_cloth.png",
+            &include_bytes!("undermine_cloth.png")[..],
+        )],
+    );
     app.textures.insert(
         "background".to_owned(),
-        Pixmap::load_png("examples/undermine_cloth.png")?,
+        image::load("examples/undermine_cloth.png")?,
     );
     event_loop.run_app(&mut app)?;
     Ok(())
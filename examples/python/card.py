@@ -0,0 +1,29 @@
+# Renders a card layout to card.png without opening a window.
+#
+#   pip install maturin && maturin develop && python examples/python/card.py
+import corsola
+
+fonts = corsola.Fonts(system_fonts=True)
+fonts.set_alias("title", "DejaVu Serif")
+
+card = corsola.Canvas(300, 420, fonts)
+card.fill((40, 30, 20))
+card.fill_rect(12, 12, 276, 396, (230, 220, 190))
+
+art = corsola.Image.load("examples/undermine_cloth.png")
+card.blit(art, 24, 60, scale=252 / art.width)
+
+title = "Undermine Cloth"
+width, _ = card.measure_text(title, 28, family="title")
+card.text(title, (300 - width) / 2, 18, 28, colour=(20, 20, 20), family="title")
+card.text(
+    "When this enters play, draw a card. The text wraps within the box.",
+    24,
+    240,
+    18,
+    colour=(40, 40, 40),
+    max_width=252,
+    align="left",
+)
+
+card.save_png("card.png")
@@ -0,0 +1,147 @@
+use corsola::{
+    anyhow, new_surface,
+    scene::{Scene, SceneChange, SceneStack},
+    tiny_skia::Color,
+    winit::{
+        application::ApplicationHandler,
+        event::{ElementState, KeyEvent, WindowEvent},
+        event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+        keyboard::{KeyCode, PhysicalKey},
+        window::WindowId,
+    },
+    Result, Surface,
+};
+
+struct Title;
+
+impl Scene for Title {
+    fn draw(&mut self, surface: &mut Surface) -> Result<()> {
+        surface.fill(Color::from_rgba8(20, 20, 60, 255));
+        surface.text(
+            "Press space to start",
+            20.0,
+            20.0,
+            60.0,
+            glyphon::Color::rgb(255, 255, 255),
+        )
+    }
+
+    fn event(&mut self, event: &WindowEvent) -> SceneChange {
+        match pressed(event) {
+            Some(KeyCode::Space) => SceneChange::Replace(Box::new(Game { time: 0.0 })),
+            Some(KeyCode::Escape) => SceneChange::Exit,
+            _ => SceneChange::None,
+        }
+    }
+}
+
+struct Game {
+    time: f32,
+}
+
+impl Scene for Game {
+    fn update(&mut self, dt: f32) -> SceneChange {
+        self.time += dt;
+        SceneChange::None
+    }
+
+    fn draw(&mut self, surface: &mut Surface) -> Result<()> {
+        surface.fill(Color::from_rgba8(20, 60, 20, 255));
+        surface.text(
+            &format!("Playing for {:.1}s, escape to pause", self.time),
+            20.0,
+            20.0,
+            60.0,
+            glyphon::Color::rgb(255, 255, 255),
+        )
+    }
+
+    fn event(&mut self, event: &WindowEvent) -> SceneChange {
+        match pressed(event) {
+            Some(KeyCode::Escape) => SceneChange::Push(Box::new(Pause)),
+            _ => SceneChange::None,
+        }
+    }
+}
+
+struct Pause;
+
+impl Scene for Pause {
+    fn draw(&mut self, surface: &mut Surface) -> Result<()> {
+        surface.text(
+            "Paused, escape to resume or Q to quit to title",
+            20.0,
+            120.0,
+            40.0,
+            glyphon::Color::rgb(255, 255, 0),
+        )
+    }
+
+    fn event(&mut self, event: &WindowEvent) -> SceneChange {
+        match pressed(event) {
+            Some(KeyCode::Escape) => SceneChange::Pop,
+            Some(KeyCode::KeyQ) => SceneChange::Replace(Box::new(Title)),
+            _ => SceneChange::None,
+        }
+    }
+
+    fn is_transparent(&self) -> bool {
+        true
+    }
+}
+
+fn pressed(event: &WindowEvent) -> Option<KeyCode> {
+    match event {
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(code),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                },
+            ..
+        } => Some(*code),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct App {
+    surface: Option<Surface>,
+    scenes: SceneStack,
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        match &mut self.surface {
+            Some(surf) => surf.attach(event_loop).unwrap(),
+            None => self.surface = Some(new_surface(event_loop, "Scenes", 1280.0, 720.0).unwrap()),
+        }
+        if self.scenes.is_empty() {
+            self.scenes.push(Box::new(Title));
+        }
+    }
+
+    // The surface keeps what was drawn and loaded while the window is gone.
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(surf) = &mut self.surface {
+            surf.detach();
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        if let Some(surf) = &mut self.surface {
+            self.scenes.window_event(event_loop, surf, event).unwrap();
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    let mut app = App::default();
+    event_loop.run_app(&mut app)?;
+    Ok(())
+}
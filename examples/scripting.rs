@@ -0,0 +1,98 @@
+use corsola::{
+    anyhow::Result,
+    audio::Audio,
+    input::{ActionMap, Binding, Bindings},
+    new_surface,
+    script::Script,
+    winit::{
+        application::ApplicationHandler,
+        event::WindowEvent,
+        event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+        keyboard::KeyCode,
+        window::WindowId,
+    },
+    Surface,
+};
+use std::time::Instant;
+
+struct App {
+    surface: Option<Surface>,
+    script: Script,
+    input: ActionMap,
+    audio: Audio,
+    last_frame: Instant,
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        match &mut self.surface {
+            Some(surf) => surf.attach(event_loop).unwrap(),
+            None => {
+                self.surface = Some(new_surface(event_loop, "Scripting", 1280.0, 720.0).unwrap())
+            }
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(surf) = &mut self.surface {
+            surf.detach();
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let Some(surf) = &mut self.surface else {
+            return;
+        };
+        self.input.handle_event(&event, surf.size());
+        match event {
+            WindowEvent::CloseRequested => {
+                event_loop.exit();
+            }
+            WindowEvent::RedrawRequested => {
+                let now = Instant::now();
+                let dt = (now - self.last_frame).as_secs_f32();
+                self.last_frame = now;
+
+                // Script errors are reported and the game carries on, so they can be fixed live.
+                if let Err(e) = self.script.reload_if_changed() {
+                    eprintln!("{}", e);
+                }
+                if let Err(e) = self.script.update(dt, &self.input, &mut self.audio) {
+                    eprintln!("{}", e);
+                }
+                if let Err(e) = self.script.draw(surf) {
+                    eprintln!("{}", e);
+                }
+                self.input.end_frame();
+
+                surf.update().unwrap();
+                surf.request_redraw();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    let mut bindings = Bindings::new();
+    bindings
+        .bind("flip", Binding::Key(KeyCode::Space))
+        .bind_axis(
+            "move",
+            [Binding::Key(KeyCode::ArrowLeft)],
+            [Binding::Key(KeyCode::ArrowRight)],
+        );
+
+    let mut app = App {
+        surface: None,
+        script: Script::load("examples/scripts/card.rhai")?,
+        input: ActionMap::new(bindings),
+        audio: Audio::new()?,
+        last_frame: Instant::now(),
+    };
+    event_loop.run_app(&mut app)?;
+    Ok(())
+}
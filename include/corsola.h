@@ -0,0 +1,123 @@
+#ifndef CORSOLA_H
+#define CORSOLA_H
+
+/* Generated from src/capi.rs by `cargo build --features header`. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum CorsolaResult {
+  CORSOLA_RESULT_OK = 0,
+  CORSOLA_RESULT_NULL_POINTER,
+  CORSOLA_RESULT_INVALID_ARGUMENT,
+  CORSOLA_RESULT_EVENT_LOOP,
+  CORSOLA_RESULT_WINDOW,
+  CORSOLA_RESULT_GPU,
+  CORSOLA_RESULT_IO,
+  CORSOLA_RESULT_IMAGE,
+  CORSOLA_RESULT_FONT,
+  CORSOLA_RESULT_TEXT,
+  CORSOLA_RESULT_RENDER,
+  CORSOLA_RESULT_DETACHED,
+  CORSOLA_RESULT_PANIC,
+  CORSOLA_RESULT_OTHER,
+} CorsolaResult;
+
+typedef struct CorsolaApp CorsolaApp;
+
+typedef struct CorsolaEventLoop CorsolaEventLoop;
+
+typedef struct CorsolaPixmap CorsolaPixmap;
+
+typedef struct CorsolaSurface CorsolaSurface;
+
+typedef struct CorsolaCallbacks {
+  void *user_data;
+  void (*resumed)(struct CorsolaApp *app, void *user_data);
+  void (*suspended)(struct CorsolaApp *app, void *user_data);
+  void (*redraw)(struct CorsolaApp *app, struct CorsolaSurface *surface, void *user_data);
+  void (*close_requested)(struct CorsolaApp *app, struct CorsolaSurface *surface, void *user_data);
+} CorsolaCallbacks;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+const char *corsola_last_error(void);
+
+enum CorsolaResult corsola_event_loop_new(struct CorsolaEventLoop **event_loop);
+
+void corsola_event_loop_free(struct CorsolaEventLoop *event_loop);
+
+enum CorsolaResult corsola_event_loop_run(struct CorsolaEventLoop *event_loop,
+                                          const struct CorsolaCallbacks *callbacks);
+
+void corsola_app_exit(struct CorsolaApp *app);
+
+enum CorsolaResult corsola_surface_new(struct CorsolaApp *app,
+                                       const char *title,
+                                       double width,
+                                       double height,
+                                       struct CorsolaSurface **surface);
+
+void corsola_surface_free(struct CorsolaSurface *surface);
+
+enum CorsolaResult corsola_surface_size(struct CorsolaSurface *surface,
+                                        uint32_t *width,
+                                        uint32_t *height);
+
+enum CorsolaResult corsola_surface_fill(struct CorsolaSurface *surface,
+                                        uint8_t r,
+                                        uint8_t g,
+                                        uint8_t b,
+                                        uint8_t a);
+
+enum CorsolaResult corsola_surface_blit(struct CorsolaSurface *surface,
+                                        struct CorsolaPixmap *pixmap,
+                                        int32_t x,
+                                        int32_t y,
+                                        float opacity);
+
+enum CorsolaResult corsola_surface_text(struct CorsolaSurface *surface,
+                                        const char *text,
+                                        float x,
+                                        float y,
+                                        float font_size,
+                                        uint8_t r,
+                                        uint8_t g,
+                                        uint8_t b,
+                                        uint8_t a);
+
+enum CorsolaResult corsola_surface_load_font(struct CorsolaSurface *surface, const char *path);
+
+enum CorsolaResult corsola_surface_update(struct CorsolaSurface *surface);
+
+enum CorsolaResult corsola_surface_request_redraw(struct CorsolaSurface *surface);
+
+enum CorsolaResult corsola_pixmap_new(uint32_t width,
+                                      uint32_t height,
+                                      struct CorsolaPixmap **pixmap);
+
+enum CorsolaResult corsola_pixmap_load_png(const char *path, struct CorsolaPixmap **pixmap);
+
+enum CorsolaResult corsola_pixmap_load(const char *path, struct CorsolaPixmap **pixmap);
+
+enum CorsolaResult corsola_pixmap_size(struct CorsolaPixmap *pixmap,
+                                       uint32_t *width,
+                                       uint32_t *height);
+
+void corsola_pixmap_free(struct CorsolaPixmap *pixmap);
+
+enum CorsolaResult corsola_vfs_mount_dir(const char *point, const char *dir);
+
+enum CorsolaResult corsola_vfs_mount_zip(const char *point, const char *path);
+
+enum CorsolaResult corsola_vfs_unmount(const char *point);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* CORSOLA_H */
@@ -0,0 +1,499 @@
+use crate::{sprite::AnimatedSprite, vfs::Vfs, Error, Result};
+use kira::{
+    manager::{
+        backend::{Backend, DefaultBackend},
+        AudioManager, AudioManagerSettings,
+    },
+    sound::{
+        static_sound::{StaticSoundData, StaticSoundHandle},
+        PlaybackState,
+    },
+    spatial::{
+        emitter::{EmitterDistances, EmitterHandle, EmitterSettings},
+        listener::{ListenerHandle, ListenerSettings},
+        scene::{SpatialSceneHandle, SpatialSceneSettings},
+    },
+    track::{TrackBuilder, TrackHandle},
+    tween::{Easing, Tween},
+    Volume,
+};
+use std::{
+    collections::HashMap, error::Error as StdError, fmt::Debug, io::Cursor, path::Path,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Master,
+    Music,
+    Sfx,
+    Voice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SoundId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlayId(u64);
+
+pub struct Audio<B: Backend = DefaultBackend> {
+    manager: AudioManager<B>,
+    music: TrackHandle,
+    sfx: TrackHandle,
+    voice: TrackHandle,
+    volumes: HashMap<Bus, f64>,
+    sounds: Vec<(StaticSoundData, Bus)>,
+    playing: HashMap<PlayId, StaticSoundHandle>,
+    next_play: u64,
+}
+
+impl Audio {
+    pub fn new() -> Result<Self> {
+        Self::new_ex(AudioManagerSettings::default())
+    }
+}
+
+impl<B: Backend> Audio<B>
+where
+    B::Error: Debug,
+{
+    // Use `Audio::<MockBackend>::new_ex(AudioManagerSettings::default())` to run without a
+    // sound card.
+    pub fn new_ex(settings: AudioManagerSettings<B>) -> Result<Self> {
+        let mut manager = AudioManager::<B>::new(settings)
+            .map_err(|e| Error::AudioBackend(format!("{:?}", e)))?;
+        let music = manager
+            .add_sub_track(TrackBuilder::new())
+            .map_err(audio_error)?;
+        let sfx = manager
+            .add_sub_track(TrackBuilder::new())
+            .map_err(audio_error)?;
+        let voice = manager
+            .add_sub_track(TrackBuilder::new())
+            .map_err(audio_error)?;
+
+        Ok(Self {
+            manager,
+            music,
+            sfx,
+            voice,
+            volumes: HashMap::new(),
+            sounds: Vec::new(),
+            playing: HashMap::new(),
+            next_play: 0,
+        })
+    }
+}
+
+impl<B: Backend> Audio<B> {
+    pub fn manager(&mut self) -> &mut AudioManager<B> {
+        &mut self.manager
+    }
+
+    // Returns `None` for `Bus::Master`, which is kira's main track.
+    pub fn track(&self, bus: Bus) -> Option<&TrackHandle> {
+        match bus {
+            Bus::Master => None,
+            Bus::Music => Some(&self.music),
+            Bus::Sfx => Some(&self.sfx),
+            Bus::Voice => Some(&self.voice),
+        }
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>, bus: Bus) -> Result<SoundId> {
+        let data = load_sound(path.as_ref())?;
+        Ok(self.add(data, bus))
+    }
+
+    pub fn load_bytes(
+        &mut self,
+        bytes: impl AsRef<[u8]> + Send + Sync + 'static,
+        bus: Bus,
+    ) -> Result<SoundId> {
+        let data =
+            StaticSoundData::from_cursor(Cursor::new(bytes)).map_err(|source| Error::Sound {
+                name: "sound data".to_string(),
+                source,
+            })?;
+        Ok(self.add(data, bus))
+    }
+
+    pub fn add(&mut self, data: StaticSoundData, bus: Bus) -> SoundId {
+        self.sounds.push((data, bus));
+        SoundId(self.sounds.len() - 1)
+    }
+
+    pub fn sound_data(&self, sound: SoundId) -> Option<&StaticSoundData> {
+        self.sounds.get(sound.0).map(|(data, _)| data)
+    }
+
+    // Swaps in new data for a sound, keeping its id and bus. Anything already playing carries on
+    // with the old data.
+    pub fn replace(&mut self, sound: SoundId, data: StaticSoundData) -> Result<()> {
+        let (old, _) = self
+            .sounds
+            .get_mut(sound.0)
+            .ok_or_else(|| Error::NotFound(format!("Sound {:?}", sound)))?;
+        *old = data;
+        Ok(())
+    }
+
+    pub fn reload(&mut self, sound: SoundId, path: impl AsRef<Path>) -> Result<()> {
+        let data = load_sound(path.as_ref())?;
+        self.replace(sound, data)
+    }
+
+    pub fn play(&mut self, sound: SoundId) -> Result<PlayId> {
+        self.play_ex(sound, |data| data)
+    }
+
+    // `settings` can adjust the sound before it starts, e.g. `|data| data.volume(0.5).loop_region(..)`
+    pub fn play_ex(
+        &mut self,
+        sound: SoundId,
+        settings: impl FnOnce(StaticSoundData) -> StaticSoundData,
+    ) -> Result<PlayId> {
+        let (data, bus) = self
+            .sounds
+            .get(sound.0)
+            .ok_or_else(|| Error::NotFound(format!("Sound {:?}", sound)))?;
+        let data = match bus {
+            Bus::Master => data.clone(),
+            Bus::Music => data.clone().output_destination(&self.music),
+            Bus::Sfx => data.clone().output_destination(&self.sfx),
+            Bus::Voice => data.clone().output_destination(&self.voice),
+        };
+        self.start((settings)(data))
+    }
+
+    pub fn play_at(&mut self, sound: SoundId, emitter: &Emitter) -> Result<PlayId> {
+        self.play_at_ex(sound, emitter, |data| data)
+    }
+
+    // Routes the sound through `emitter` instead of the sound's bus. The emitter's scene decides
+    // which bus it ends up on.
+    pub fn play_at_ex(
+        &mut self,
+        sound: SoundId,
+        emitter: &Emitter,
+        settings: impl FnOnce(StaticSoundData) -> StaticSoundData,
+    ) -> Result<PlayId> {
+        let (data, _) = self
+            .sounds
+            .get(sound.0)
+            .ok_or_else(|| Error::NotFound(format!("Sound {:?}", sound)))?;
+        let data = data.clone().output_destination(&emitter.handle);
+        self.start((settings)(data))
+    }
+
+    fn start(&mut self, data: StaticSoundData) -> Result<PlayId> {
+        let handle = self.manager.play(data).map_err(audio_error)?;
+
+        self.cleanup();
+        let id = PlayId(self.next_play);
+        self.next_play += 1;
+        self.playing.insert(id, handle);
+        Ok(id)
+    }
+
+    // Creates a 2D spatial scene whose listener outputs to `bus`, usually `Bus::Sfx`.
+    pub fn add_spatial(&mut self, bus: Bus) -> Result<SpatialAudio> {
+        let mut scene = self
+            .manager
+            .add_spatial_scene(SpatialSceneSettings::default())
+            .map_err(audio_error)?;
+        let settings = match self.track(bus) {
+            Some(track) => ListenerSettings::new().track(track.id()),
+            None => ListenerSettings::new(),
+        };
+        let listener = scene
+            .add_listener([0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0], settings)
+            .map_err(audio_error)?;
+
+        Ok(SpatialAudio {
+            scene,
+            listener,
+            listener_pos: (0.0, 0.0),
+            distances: EmitterDistances {
+                min_distance: 100.0,
+                max_distance: 1000.0,
+            },
+            attenuation: Some(Easing::Linear),
+        })
+    }
+
+    pub fn stop(&mut self, play: PlayId, fade: Duration) {
+        if let Some(mut handle) = self.playing.remove(&play) {
+            handle.stop(tween(fade));
+        }
+    }
+
+    pub fn pause(&mut self, play: PlayId, fade: Duration) {
+        if let Some(handle) = self.playing.get_mut(&play) {
+            handle.pause(tween(fade));
+        }
+    }
+
+    pub fn resume(&mut self, play: PlayId, fade: Duration) {
+        if let Some(handle) = self.playing.get_mut(&play) {
+            handle.resume(tween(fade));
+        }
+    }
+
+    pub fn stop_all(&mut self, fade: Duration) {
+        for (_, mut handle) in self.playing.drain() {
+            handle.stop(tween(fade));
+        }
+    }
+
+    pub fn state(&self, play: PlayId) -> PlaybackState {
+        self.playing
+            .get(&play)
+            .map_or(PlaybackState::Stopped, |handle| handle.state())
+    }
+
+    pub fn is_playing(&self, play: PlayId) -> bool {
+        self.state(play) != PlaybackState::Stopped
+    }
+
+    pub fn handle(&mut self, play: PlayId) -> Option<&mut StaticSoundHandle> {
+        self.playing.get_mut(&play)
+    }
+
+    pub fn volume(&self, bus: Bus) -> f64 {
+        self.volumes.get(&bus).copied().unwrap_or(1.0)
+    }
+
+    pub fn set_volume(&mut self, bus: Bus, volume: f64, fade: Duration) {
+        self.volumes.insert(bus, volume);
+        let volume = Volume::Amplitude(volume);
+        match bus {
+            Bus::Master => self.manager.main_track().set_volume(volume, tween(fade)),
+            Bus::Music => self.music.set_volume(volume, tween(fade)),
+            Bus::Sfx => self.sfx.set_volume(volume, tween(fade)),
+            Bus::Voice => self.voice.set_volume(volume, tween(fade)),
+        }
+    }
+
+    // Drops handles of sounds that have finished playing.
+    pub fn cleanup(&mut self) {
+        self.playing
+            .retain(|_, handle| handle.state() != PlaybackState::Stopped);
+    }
+}
+
+fn load_sound(path: &Path) -> Result<StaticSoundData> {
+    let bytes = Vfs::global().read(path)?;
+    StaticSoundData::from_cursor(Cursor::new(bytes)).map_err(|source| Error::Sound {
+        name: path.display().to_string(),
+        source,
+    })
+}
+
+// For kira's errors about running out of sounds, tracks and emitters, or the audio thread.
+pub(crate) fn audio_error(e: impl StdError + Send + Sync + 'static) -> Error {
+    Error::Audio(Box::new(e))
+}
+
+// Positions are in world pixels with y pointing down, matching `Surface` coordinates. kira's
+// listener looks down -z with +y up, so a position (x, y) is placed at [x, -y, 0] in its scene.
+// Sounds are panned by the horizontal offset from the listener and fade out between the emitter
+// distances.
+pub struct SpatialAudio {
+    scene: SpatialSceneHandle,
+    listener: ListenerHandle,
+    listener_pos: (f32, f32),
+    pub distances: EmitterDistances,
+    pub attenuation: Option<Easing>,
+}
+
+impl SpatialAudio {
+    pub fn listener(&self) -> (f32, f32) {
+        self.listener_pos
+    }
+
+    // Call with the camera centre each frame to keep panning in step with the view.
+    pub fn set_listener(&mut self, x: f32, y: f32) {
+        self.listener_pos = (x, y);
+        self.listener.set_position(to_scene(x, y), Tween::default());
+    }
+
+    pub fn add_emitter(&mut self, x: f32, y: f32) -> Result<Emitter> {
+        let settings = EmitterSettings::new()
+            .distances(self.distances)
+            .attenuation_function(self.attenuation);
+        self.add_emitter_ex(x, y, settings)
+    }
+
+    pub fn add_emitter_ex(&mut self, x: f32, y: f32, settings: EmitterSettings) -> Result<Emitter> {
+        let handle = self
+            .scene
+            .add_emitter(to_scene(x, y), settings)
+            .map_err(audio_error)?;
+        Ok(Emitter {
+            handle,
+            pos: (x, y),
+        })
+    }
+}
+
+// Dropping an emitter removes it from the scene once its sounds finish.
+pub struct Emitter {
+    handle: EmitterHandle,
+    pos: (f32, f32),
+}
+
+impl Emitter {
+    pub fn position(&self) -> (f32, f32) {
+        self.pos
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        if self.pos == (x, y) {
+            return;
+        }
+        self.pos = (x, y);
+        self.handle.set_position(to_scene(x, y), Tween::default());
+    }
+
+    // Call each frame with where the sprite is drawn to keep the emitter at its centre.
+    pub fn follow(&mut self, sprite: &AnimatedSprite, x: i32, y: i32) {
+        let image = sprite.image();
+        self.set_position(
+            x as f32 + image.width() as f32 / 2.0,
+            y as f32 + image.height() as f32 / 2.0,
+        );
+    }
+}
+
+fn to_scene(x: f32, y: f32) -> [f32; 3] {
+    [x, -y, 0.0]
+}
+
+pub(crate) fn tween(duration: Duration) -> Tween {
+    Tween {
+        duration,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kira::manager::backend::mock::{MockBackend, MockBackendSettings};
+    use std::fs;
+
+    const RATE: u32 = 100;
+
+    fn audio() -> Audio<MockBackend> {
+        Audio::new_ex(AudioManagerSettings {
+            backend_settings: MockBackendSettings { sample_rate: RATE },
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    // A mono 16-bit WAV, `seconds` long with every sample at `value`.
+    fn wav(seconds: u32, value: i16) -> Vec<u8> {
+        let len = seconds * RATE * 2;
+        let mut data = Vec::new();
+        data.extend_from_slice(b"RIFF");
+        data.extend_from_slice(&(36 + len).to_le_bytes());
+        data.extend_from_slice(b"WAVEfmt ");
+        data.extend_from_slice(&16u32.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&RATE.to_le_bytes());
+        data.extend_from_slice(&(RATE * 2).to_le_bytes());
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&16u16.to_le_bytes());
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&len.to_le_bytes());
+        for _ in 0..seconds * RATE {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data
+    }
+
+    // Runs the pending commands, lets volume changes settle, then sums the output over half a
+    // second.
+    fn level(audio: &mut Audio<MockBackend>) -> f32 {
+        let backend = audio.manager().backend_mut();
+        backend.on_start_processing();
+        for _ in 0..10 {
+            backend.process();
+        }
+        (0..RATE / 2).map(|_| backend.process().left.abs()).sum()
+    }
+
+    #[test]
+    fn bus_volumes() {
+        let mut audio = audio();
+        let music = audio.load_bytes(wav(10, i16::MAX / 2), Bus::Music).unwrap();
+        let sfx = audio.load_bytes(wav(10, i16::MAX / 2), Bus::Sfx).unwrap();
+        audio.set_volume(Bus::Music, 0.0, Duration::ZERO);
+        assert_eq!(audio.volume(Bus::Music), 0.0);
+        assert_eq!(audio.volume(Bus::Sfx), 1.0);
+        level(&mut audio);
+
+        audio.play(music).unwrap();
+        assert_eq!(level(&mut audio), 0.0);
+        let play = audio.play(sfx).unwrap();
+        assert!(level(&mut audio) > 0.0);
+
+        audio.set_volume(Bus::Master, 0.0, Duration::ZERO);
+        assert_eq!(level(&mut audio), 0.0);
+        assert!(audio.is_playing(play));
+    }
+
+    #[test]
+    fn replace_keeps_id_and_bus() {
+        let mut audio = audio();
+        let sound = audio.load_bytes(wav(1, 0), Bus::Music).unwrap();
+        let data = StaticSoundData::from_cursor(Cursor::new(wav(10, i16::MAX / 2))).unwrap();
+        audio.replace(sound, data).unwrap();
+        assert_eq!(
+            audio.sound_data(sound).unwrap().duration(),
+            Duration::from_secs(10)
+        );
+
+        audio.set_volume(Bus::Music, 0.0, Duration::ZERO);
+        level(&mut audio);
+        audio.play(sound).unwrap();
+        assert_eq!(level(&mut audio), 0.0);
+        audio.set_volume(Bus::Music, 1.0, Duration::ZERO);
+        assert!(level(&mut audio) > 0.0);
+
+        let data = audio.sound_data(sound).unwrap().clone();
+        assert!(audio.replace(SoundId(1), data).is_err());
+    }
+
+    #[test]
+    fn reload() {
+        let path = std::env::temp_dir().join(format!("corsola-reload-{}.wav", std::process::id()));
+        fs::write(&path, wav(1, 0)).unwrap();
+        let mut audio = audio();
+        let sound = audio.load(&path, Bus::Sfx).unwrap();
+        assert_eq!(
+            audio.sound_data(sound).unwrap().duration(),
+            Duration::from_secs(1)
+        );
+
+        fs::write(&path, wav(2, 0)).unwrap();
+        audio.reload(sound, &path).unwrap();
+        assert_eq!(
+            audio.sound_data(sound).unwrap().duration(),
+            Duration::from_secs(2)
+        );
+
+        // a bad file leaves the last good version loaded
+        fs::write(&path, b"not a sound").unwrap();
+        assert!(audio.reload(sound, &path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(audio.reload(sound, &path).is_err());
+        assert_eq!(
+            audio.sound_data(sound).unwrap().duration(),
+            Duration::from_secs(2)
+        );
+    }
+}
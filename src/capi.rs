@@ -0,0 +1,526 @@
+// C API for the staticlib and cdylib builds. include/corsola.h is generated from this file by
+// `cargo build --features header`. Every handle is an opaque pointer owned by the caller until
+// passed to the matching `_free` function, and every pointer argument must be null or a live
+// handle from this API.
+// Surfaces are only valid until `corsola_event_loop_run` returns.
+use crate::{image, new_surface, vfs::Vfs, Error, Surface};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_void, CStr, CString},
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+use tiny_skia::{Color, Pixmap, PixmapPaint, Transform};
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop},
+    window::WindowId,
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorsolaResult {
+    Ok = 0,
+    NullPointer,
+    InvalidArgument,
+    EventLoop,
+    Window,
+    Gpu,
+    Io,
+    Image,
+    Font,
+    Text,
+    Render,
+    Detached,
+    // a bug in corsola, the handles involved may be left in an inconsistent state
+    Panic,
+    // from parts of corsola the C API doesn't cover yet
+    Other,
+}
+
+pub struct CorsolaEventLoop(EventLoop<()>);
+
+pub struct CorsolaSurface(Surface);
+
+pub struct CorsolaPixmap(Pixmap);
+
+// Only valid for the duration of the callback it's passed to.
+pub struct CorsolaApp<'a> {
+    event_loop: &'a ActiveEventLoop,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CorsolaCallbacks {
+    pub user_data: *mut c_void,
+    // Surfaces can only be created once this has been called. After a suspend, existing surfaces
+    // are attached to new windows before it's called again.
+    pub resumed: Option<unsafe extern "C" fn(app: *mut CorsolaApp, user_data: *mut c_void)>,
+    pub suspended: Option<unsafe extern "C" fn(app: *mut CorsolaApp, user_data: *mut c_void)>,
+    pub redraw: Option<
+        unsafe extern "C" fn(
+            app: *mut CorsolaApp,
+            surface: *mut CorsolaSurface,
+            user_data: *mut c_void,
+        ),
+    >,
+    // Exits the event loop when null.
+    pub close_requested: Option<
+        unsafe extern "C" fn(
+            app: *mut CorsolaApp,
+            surface: *mut CorsolaSurface,
+            user_data: *mut c_void,
+        ),
+    >,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+    // live surfaces, for routing window events and freeing what's left when the loop ends
+    static SURFACES: RefCell<Vec<*mut CorsolaSurface>> = const { RefCell::new(Vec::new()) };
+}
+
+// A copy, so callbacks can create and free surfaces while the caller walks it.
+fn surfaces() -> Vec<*mut CorsolaSurface> {
+    SURFACES.with(|surfaces| surfaces.borrow().clone())
+}
+
+struct Failure(CorsolaResult, String);
+
+impl Failure {
+    fn new(code: CorsolaResult, message: impl Display) -> Self {
+        Self(code, message.to_string())
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(e: Error) -> Self {
+        let code = match &e {
+            Error::Window(_) => CorsolaResult::Window,
+            Error::Gpu(_) => CorsolaResult::Gpu,
+            Error::Detached | Error::SurfaceLost(_) => CorsolaResult::Detached,
+            Error::Pixmap { .. }
+            | Error::PaletteSize(_)
+            | Error::IndexCount { .. }
+            | Error::NotFound(_)
+            | Error::NoPath(_) => CorsolaResult::InvalidArgument,
+            Error::Io { .. } | Error::Archive { .. } => CorsolaResult::Io,
+            Error::InvalidFont(_) | Error::FamilyNotFound(_) => CorsolaResult::Font,
+            Error::PrepareText(_) | Error::RenderText(_) => CorsolaResult::Text,
+            Error::Render(_) | Error::Screenshot(_) => CorsolaResult::Render,
+            Error::Png(_)
+            | Error::UnsupportedImage(_)
+            | Error::Image { .. }
+            | Error::NotInPalette { .. }
+            | Error::Gif(_) => CorsolaResult::Image,
+            Error::AudioBackend(_)
+            | Error::Audio(_)
+            | Error::Sound { .. }
+            | Error::Bindings(_)
+            | Error::Watch(_)
+            | Error::Script { .. }
+            | Error::Recording(_) => CorsolaResult::Other,
+        };
+        Self::new(code, e)
+    }
+}
+
+fn report(Failure(code, message): Failure) -> CorsolaResult {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    code
+}
+
+// Panics are caught here rather than unwinding into C, which is undefined behaviour.
+fn status(f: impl FnOnce() -> Result<(), Failure>) -> CorsolaResult {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => CorsolaResult::Ok,
+        Ok(Err(failure)) => report(failure),
+        Err(payload) => report(Failure::new(CorsolaResult::Panic, panic_message(&*payload))),
+    }
+}
+
+// For functions that don't return a result. A panic is still recorded for `corsola_last_error`.
+fn guard(f: impl FnOnce()) {
+    status(|| {
+        f();
+        Ok(())
+    });
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    format!("Panic: {}", message)
+}
+
+unsafe fn handle<'a, T>(p: *mut T) -> Result<&'a mut T, Failure> {
+    p.as_mut()
+        .ok_or_else(|| Failure::new(CorsolaResult::NullPointer, "Null handle"))
+}
+
+unsafe fn string<'a>(s: *const c_char) -> Result<&'a str, Failure> {
+    if s.is_null() {
+        return Err(Failure::new(CorsolaResult::NullPointer, "Null string"));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| Failure::new(CorsolaResult::InvalidArgument, e))
+}
+
+unsafe fn out<T>(out: *mut *mut T, value: T) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err(Failure::new(
+            CorsolaResult::NullPointer,
+            "Null output pointer",
+        ));
+    }
+    *out = Box::into_raw(Box::new(value));
+    Ok(())
+}
+
+// The message for the last error on this thread, valid until the next failing call. Null if
+// nothing has failed yet.
+#[no_mangle]
+pub extern "C" fn corsola_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_event_loop_new(
+    event_loop: *mut *mut CorsolaEventLoop,
+) -> CorsolaResult {
+    status(|| {
+        let new = EventLoop::new().map_err(|e| Failure::new(CorsolaResult::EventLoop, e))?;
+        out(event_loop, CorsolaEventLoop(new))
+    })
+}
+
+// For an event loop that is never run.
+#[no_mangle]
+pub unsafe extern "C" fn corsola_event_loop_free(event_loop: *mut CorsolaEventLoop) {
+    guard(|| {
+        if !event_loop.is_null() {
+            drop(Box::from_raw(event_loop));
+        }
+    })
+}
+
+// Takes ownership of the event loop and blocks until `corsola_app_exit` is called. Any surfaces
+// not freed by then are freed on return.
+#[no_mangle]
+pub unsafe extern "C" fn corsola_event_loop_run(
+    event_loop: *mut CorsolaEventLoop,
+    callbacks: *const CorsolaCallbacks,
+) -> CorsolaResult {
+    status(|| {
+        if event_loop.is_null() {
+            return Err(Failure::new(CorsolaResult::NullPointer, "Null event loop"));
+        }
+        let event_loop = Box::from_raw(event_loop).0;
+        let callbacks = *callbacks
+            .as_ref()
+            .ok_or_else(|| Failure::new(CorsolaResult::NullPointer, "Null callbacks"))?;
+        let mut handler = Handler { callbacks };
+        event_loop
+            .run_app(&mut handler)
+            .map_err(|e| Failure::new(CorsolaResult::EventLoop, e))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_app_exit(app: *mut CorsolaApp) {
+    guard(|| {
+        if let Some(app) = app.as_mut() {
+            app.event_loop.exit();
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_new(
+    app: *mut CorsolaApp,
+    title: *const c_char,
+    width: f64,
+    height: f64,
+    surface: *mut *mut CorsolaSurface,
+) -> CorsolaResult {
+    status(|| {
+        let app = handle(app)?;
+        let new = new_surface(app.event_loop, string(title)?, width, height)?;
+        out(surface, CorsolaSurface(new))?;
+        SURFACES.with(|surfaces| surfaces.borrow_mut().push(*surface));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_free(surface: *mut CorsolaSurface) {
+    guard(|| {
+        if !surface.is_null() {
+            SURFACES.with(|surfaces| surfaces.borrow_mut().retain(|&s| s != surface));
+            drop(Box::from_raw(surface));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_size(
+    surface: *mut CorsolaSurface,
+    width: *mut u32,
+    height: *mut u32,
+) -> CorsolaResult {
+    status(|| {
+        let (w, h) = handle(surface)?.0.size();
+        *handle(width)? = w;
+        *handle(height)? = h;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_fill(
+    surface: *mut CorsolaSurface,
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+) -> CorsolaResult {
+    status(|| {
+        handle(surface)?.0.fill(Color::from_rgba8(r, g, b, a));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_blit(
+    surface: *mut CorsolaSurface,
+    pixmap: *mut CorsolaPixmap,
+    x: i32,
+    y: i32,
+    opacity: f32,
+) -> CorsolaResult {
+    status(|| {
+        let pixmap = &handle(pixmap)?.0;
+        let paint = PixmapPaint {
+            opacity,
+            ..Default::default()
+        };
+        handle(surface)?
+            .0
+            .blit(x, y, pixmap, &paint, Transform::identity(), None);
+        Ok(())
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_text(
+    surface: *mut CorsolaSurface,
+    text: *const c_char,
+    x: f32,
+    y: f32,
+    font_size: f32,
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+) -> CorsolaResult {
+    status(|| {
+        let colour = glyphon::Color::rgba(r, g, b, a);
+        Ok(handle(surface)?
+            .0
+            .text(string(text)?, x, y, font_size, colour)?)
+    })
+}
+
+// Loads a font file into the fonts shared by the surface.
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_load_font(
+    surface: *mut CorsolaSurface,
+    path: *const c_char,
+) -> CorsolaResult {
+    status(|| {
+        handle(surface)?.0.fonts().load_font_file(string(path)?)?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_update(surface: *mut CorsolaSurface) -> CorsolaResult {
+    status(|| Ok(handle(surface)?.0.update()?))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_surface_request_redraw(
+    surface: *mut CorsolaSurface,
+) -> CorsolaResult {
+    status(|| {
+        handle(surface)?.0.request_redraw();
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_pixmap_new(
+    width: u32,
+    height: u32,
+    pixmap: *mut *mut CorsolaPixmap,
+) -> CorsolaResult {
+    status(|| {
+        let new = Pixmap::new(width, height).ok_or(Error::Pixmap { width, height })?;
+        out(pixmap, CorsolaPixmap(new))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_pixmap_load_png(
+    path: *const c_char,
+    pixmap: *mut *mut CorsolaPixmap,
+) -> CorsolaResult {
+    status(|| {
+        let path = string(path)?;
+        let data = Vfs::global().read(path)?;
+        let new = Pixmap::decode_png(&data).map_err(|e| {
+            Failure::new(
+                CorsolaResult::Image,
+                format!("Error decoding {}: {}", path, e),
+            )
+        })?;
+        out(pixmap, CorsolaPixmap(new))
+    })
+}
+
+// Loads a PNG, JPEG, WebP, QOI, BMP, TGA or GIF.
+#[no_mangle]
+pub unsafe extern "C" fn corsola_pixmap_load(
+    path: *const c_char,
+    pixmap: *mut *mut CorsolaPixmap,
+) -> CorsolaResult {
+    status(|| out(pixmap, CorsolaPixmap(image::load(string(path)?)?)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_pixmap_size(
+    pixmap: *mut CorsolaPixmap,
+    width: *mut u32,
+    height: *mut u32,
+) -> CorsolaResult {
+    status(|| {
+        let pixmap = &handle(pixmap)?.0;
+        *handle(width)? = pixmap.width();
+        *handle(height)? = pixmap.height();
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_pixmap_free(pixmap: *mut CorsolaPixmap) {
+    guard(|| {
+        if !pixmap.is_null() {
+            drop(Box::from_raw(pixmap));
+        }
+    })
+}
+
+// Mounts a directory or zip archive into the virtual filesystem every path above is read through.
+// An empty `point` mounts at the root. Later mounts take priority over earlier ones.
+#[no_mangle]
+pub unsafe extern "C" fn corsola_vfs_mount_dir(
+    point: *const c_char,
+    dir: *const c_char,
+) -> CorsolaResult {
+    status(|| Ok(Vfs::global().mount_dir(string(point)?, string(dir)?)?))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_vfs_mount_zip(
+    point: *const c_char,
+    path: *const c_char,
+) -> CorsolaResult {
+    status(|| Ok(Vfs::global().mount_zip(string(point)?, string(path)?)?))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn corsola_vfs_unmount(point: *const c_char) -> CorsolaResult {
+    status(|| {
+        Vfs::global().unmount(string(point)?);
+        Ok(())
+    })
+}
+
+struct Handler {
+    callbacks: CorsolaCallbacks,
+}
+
+impl Handler {
+    fn find(&self, id: WindowId) -> Option<*mut CorsolaSurface> {
+        SURFACES.with(|surfaces| {
+            surfaces.borrow().iter().copied().find(|&s| {
+                // SAFETY: surfaces are removed from the list when they're freed
+                unsafe { (*s).0.window().map(|w| w.id()) == Some(id) }
+            })
+        })
+    }
+}
+
+impl ApplicationHandler for Handler {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        for surface in surfaces() {
+            // SAFETY: as in `find`
+            if let Err(e) = unsafe { (*surface).0.attach(event_loop) } {
+                report(e.into());
+            }
+        }
+        if let Some(resumed) = self.callbacks.resumed {
+            let mut app = CorsolaApp { event_loop };
+            unsafe { resumed(&mut app, self.callbacks.user_data) };
+        }
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(suspended) = self.callbacks.suspended {
+            let mut app = CorsolaApp { event_loop };
+            unsafe { suspended(&mut app, self.callbacks.user_data) };
+        }
+        for surface in surfaces() {
+            // SAFETY: as in `find`
+            unsafe { (*surface).0.detach() };
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        let Some(surface) = self.find(id) else {
+            return;
+        };
+        let callback = match event {
+            WindowEvent::RedrawRequested => self.callbacks.redraw,
+            WindowEvent::CloseRequested => match self.callbacks.close_requested {
+                Some(close_requested) => Some(close_requested),
+                None => {
+                    event_loop.exit();
+                    None
+                }
+            },
+            _ => None,
+        };
+        if let Some(callback) = callback {
+            let mut app = CorsolaApp { event_loop };
+            unsafe { callback(&mut app, surface, self.callbacks.user_data) };
+        }
+    }
+}
+
+impl Drop for Handler {
+    fn drop(&mut self) {
+        for surface in SURFACES.with(|surfaces| surfaces.take()) {
+            // SAFETY: as in `find`
+            drop(unsafe { Box::from_raw(surface) });
+        }
+    }
+}
@@ -1,13 +1,22 @@
-use anyhow::{anyhow, Result};
+use crate::{
+    filter::FilterChain,
+    font::{text_bounds, FontContext, Fonts},
+    Error, Result,
+};
 use glyphon::{
-    cosmic_text::Align, fontdb::Source, Attrs, Buffer, Color, FontSystem, Metrics, Resolution,
-    Shaping, SwashCache, TextArea, TextAtlas, TextBounds, TextRenderer, Wrap,
+    cosmic_text::Align, fontdb::Source, Attrs, Color, Resolution, Shaping, TextArea, TextAtlas,
+    TextBounds, TextRenderer, Wrap,
 };
 use pixels::{wgpu::MultisampleState, Pixels, SurfaceTexture};
 use self_cell::self_cell;
-use tiny_skia::{Mask, Pixmap, PixmapPaint, Transform};
+use std::{path::Path as FilePath, sync::mpsc};
+use tiny_skia::{FillRule, Mask, Paint, Path, Pixmap, PixmapPaint, Transform};
 use wgpu::{
-    LoadOp, Operations, RenderPassColorAttachment, RenderPassDescriptor, StoreOp, TextureFormat,
+    BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d,
+    ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, LoadOp, Maintain, MapMode, Operations,
+    Origin3d, RenderPassColorAttachment, RenderPassDescriptor, StoreOp, SurfaceError,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureViewDescriptor, COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 use winit::{
     dpi::LogicalSize,
@@ -21,29 +30,33 @@ const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
 #[cfg(not(target_os = "android"))]
 const FORMAT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
 
+// The part of a `Surface` tied to the window and GPU device. It's dropped when the surface is
+// detached and rebuilt when it's attached again or the GPU surface is lost.
 pub struct Renderer<'a> {
     pub pixels: Pixels<'a>,
-    pub surface: Pixmap,
     text_renderers: Vec<TextRenderer>,
     num_text: usize,
+    // text drawn in the last presented frame, kept for screenshots
+    presented_text: usize,
     font_atlas: TextAtlas,
+    // the `Fonts` generation glyphs in the atlas were rasterised from
+    font_generation: u64,
     // clear_colour: wgpu::Color,
-    glyph_cache: SwashCache,
-    font_sys: Option<FontSystem>,
-    fonts: Vec<Source>,
+    fonts: FontContext,
 }
 
 impl<'a> Renderer<'a> {
     pub fn new(window: &'a Window) -> Result<Self> {
-        let win_size = window.inner_size();
+        Self::new_with_fonts(window, FontContext::new())
+    }
 
-        let surface = Pixmap::new(win_size.width, win_size.height)
-            .ok_or(anyhow!("Error initialising Pixmap surface"))?;
+    pub fn new_with_fonts(window: &'a Window, fonts: FontContext) -> Result<Self> {
+        let win_size = window.inner_size();
 
         let pixels = {
             let surf_tex = SurfaceTexture::new(win_size.width, win_size.height, window);
 
-            Pixels::new(win_size.width, win_size.height, surf_tex)?
+            Pixels::new(win_size.width, win_size.height, surf_tex).map_err(Error::Gpu)?
         };
         let device = pixels.device();
         let queue = pixels.queue();
@@ -53,37 +66,23 @@ impl<'a> Renderer<'a> {
 
         Ok(Self {
             pixels,
-            surface,
             text_renderers: Vec::new(),
             num_text: 0,
+            presented_text: 0,
             font_atlas,
+            font_generation: 0,
             // clear_colour: wgpu::Color {
             //     r: 0.0,
             //     g: 0.0,
             //     b: 0.0,
             //     a: 0.0,
             // },
-            glyph_cache: SwashCache::new(),
-            font_sys: None,
-            fonts: Vec::new(),
+            fonts,
         })
     }
 
-    pub fn blit(
-        &mut self,
-        x: i32,
-        y: i32,
-        pixmap: &Pixmap,
-        paint: &PixmapPaint,
-        transform: Transform,
-        mask: Option<&Mask>,
-    ) {
-        self.surface
-            .draw_pixmap(x, y, pixmap.as_ref(), paint, transform, mask);
-    }
-
-    pub fn update(&mut self) -> Result<()> {
-        self.pixels.frame_mut().copy_from_slice(self.surface.data());
+    pub fn update(&mut self, pixmap: &Pixmap) -> Result<()> {
+        self.pixels.frame_mut().copy_from_slice(pixmap.data());
         // self.pixels.render()?;
 
         self.pixels.render_with(|encoder, render_target, context| {
@@ -113,17 +112,139 @@ impl<'a> Renderer<'a> {
             Ok(())
         })?;
 
+        self.presented_text = self.num_text;
         self.num_text = 0;
         // self.text_renderers.clear();
 
         Ok(())
     }
 
-    pub fn load_fonts(&mut self, fonts: impl IntoIterator<Item = Source>, update: bool) {
-        self.fonts.extend(fonts);
-        if update {
-            self.font_sys = Some(FontSystem::new_with_fonts(self.fonts.clone()));
+    // Renders the last frame passed to `update` again, text included, into an offscreen texture
+    // and reads it back. Call it after `update` and before drawing any text for the next frame.
+    pub fn screenshot(&mut self) -> Result<Pixmap> {
+        let context = self.pixels.context();
+        let device = self.pixels.device();
+        let queue = self.pixels.queue();
+        let (clip_x, clip_y, clip_w, clip_h) = context.scaling_renderer.clip_rect();
+        let extent = Extent3d {
+            width: (clip_x + clip_w).max(context.texture_extent.width),
+            height: (clip_y + clip_h).max(context.texture_extent.height),
+            depth_or_array_layers: 1,
+        };
+
+        let target = device.create_texture(&TextureDescriptor {
+            label: Some("screenshot"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&TextureViewDescriptor::default());
+
+        let row_bytes = extent.width * 4;
+        let padded_row_bytes =
+            row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("screenshot readback"),
+            size: (padded_row_bytes * extent.height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("screenshot"),
+        });
+        context.scaling_renderer.render(&mut encoder, &view);
+        {
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("screenshot text rendering"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            for t_rend in &self.text_renderers[..self.presented_text] {
+                t_rend.render(&self.font_atlas, &mut pass)?;
+            }
+        }
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &target,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(extent.height),
+                },
+            },
+            extent,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(Maintain::Wait);
+        // the callback has run once the wait returns, a missing result means it was dropped
+        receiver
+            .recv()
+            .unwrap_or(Err(BufferAsyncError))
+            .map_err(Error::Screenshot)?;
+
+        let mut shot = Pixmap::new(clip_w, clip_h).ok_or(Error::Pixmap {
+            width: clip_w,
+            height: clip_h,
+        })?;
+        {
+            let mapped = slice.get_mapped_range();
+            let out = shot.data_mut();
+            for y in 0..clip_h {
+                let src = ((clip_y + y) * padded_row_bytes + clip_x * 4) as usize;
+                let dst = (y * clip_w * 4) as usize;
+                let len = (clip_w * 4) as usize;
+                out[dst..dst + len].copy_from_slice(&mapped[src..src + len]);
+            }
+        }
+        buffer.unmap();
+
+        for px in shot.data_mut().chunks_exact_mut(4) {
+            if FORMAT == TextureFormat::Bgra8UnormSrgb {
+                px.swap(0, 2);
+            }
+            // the window is opaque, so what's visible is the colour over black
+            px[3] = 255;
         }
+        Ok(shot)
+    }
+
+    pub fn save_screenshot(&mut self, path: impl AsRef<FilePath>) -> Result<()> {
+        self.screenshot()?.save_png(path)?;
+        Ok(())
+    }
+
+    pub fn fonts(&self) -> &FontContext {
+        &self.fonts
+    }
+
+    pub fn load_fonts(&mut self, fonts: impl IntoIterator<Item = Source>, update: bool) {
+        self.fonts.load_fonts(fonts, update);
     }
 
     pub fn text_ex(
@@ -134,77 +255,58 @@ impl<'a> Renderer<'a> {
         font_size: f32,
         params: TextParams,
     ) -> Result<()> {
-        if self.font_sys.is_none() {
-            self.font_sys = Some(FontSystem::new_with_fonts(self.fonts.clone()));
+        let device = self.pixels.device();
+        let queue = self.pixels.queue();
+        let tex = &self.pixels.context().texture;
+        let width = tex.width();
+        let height = tex.height();
+
+        let mut font_ctx = self.fonts.lock();
+        let buf = font_ctx.layout(txt, font_size, &params, (width as f32, height as f32))?;
+        // only between frames, as text already prepared this frame uses the old atlas
+        if font_ctx.generation != self.font_generation && self.num_text == 0 {
+            self.font_atlas = TextAtlas::new(device, queue, FORMAT);
+            self.text_renderers.clear();
+            self.presented_text = 0;
+            self.font_generation = font_ctx.generation;
         }
-        if let Some(fonts) = &mut self.font_sys {
-            let device = self.pixels.device();
-            let queue = self.pixels.queue();
-            let tex = &self.pixels.context().texture;
-            let width = tex.width();
-            let height = tex.height();
-            let line_height = match params.line_height {
-                Some(lh) => lh,
-                None => font_size * 1.5,
-            };
-            let mut buf = Buffer::new(fonts, Metrics::new(font_size, line_height));
-            buf.set_wrap(fonts, params.wrap);
-            let (dimx, dimy) = match params.dimensions {
-                Some((x, y)) => {
-                    // buf.set_size(fonts, x, y);
-                    (x, y)
-                }
-                None => (width as f32, height as f32),
-            };
-            buf.set_size(fonts, dimx, dimy);
-            buf.set_text(fonts, txt, params.attrs, params.shaping);
-
-            for line in buf.lines.iter_mut() {
-                line.set_align(params.align);
-            }
-
-            buf.shape_until_scroll(fonts);
+        let Fonts {
+            font_sys: Some(fonts),
+            glyph_cache,
+            ..
+        } = &mut *font_ctx
+        else {
+            return Ok(());
+        };
 
-            if self.text_renderers.len() <= self.num_text {
-                self.text_renderers.push(TextRenderer::new(
-                    &mut self.font_atlas,
-                    device,
-                    MultisampleState::default(),
-                    None,
-                ));
-            }
-            let (boundsx, boundsy) = buf.size();
-            self.text_renderers
-                .get_mut(self.num_text)
-                .unwrap()
-                .prepare(
-                    device,
-                    queue,
-                    fonts,
-                    &mut self.font_atlas,
-                    Resolution { width, height },
-                    [TextArea {
-                        buffer: &buf,
-                        left: x,
-                        top: y,
-                        scale: params.scale,
-                        bounds: match params.bounds {
-                            Some(bounds) => bounds,
-                            None => TextBounds {
-                                left: x as i32,
-                                top: y as i32,
-                                right: boundsx as i32,
-                                bottom: boundsy as i32,
-                                // right: width as i32,
-                                // bottom: (y + line_height * buf.lines.len()) as i32,
-                            },
-                        },
-                        default_color: params.colour,
-                    }],
-                    &mut self.glyph_cache,
-                )?;
-            self.num_text += 1;
+        if self.text_renderers.len() <= self.num_text {
+            self.text_renderers.push(TextRenderer::new(
+                &mut self.font_atlas,
+                device,
+                MultisampleState::default(),
+                None,
+            ));
         }
+        self.text_renderers
+            .get_mut(self.num_text)
+            .unwrap()
+            .prepare(
+                device,
+                queue,
+                fonts,
+                &mut self.font_atlas,
+                Resolution { width, height },
+                [TextArea {
+                    buffer: &buf,
+                    left: x,
+                    top: y,
+                    scale: params.scale,
+                    bounds: text_bounds(&params, x, y, &buf),
+                    default_color: params.colour,
+                }],
+                glyph_cache,
+            )?;
+        self.num_text += 1;
         Ok(())
     }
 
@@ -223,45 +325,154 @@ impl<'a> Renderer<'a> {
 }
 
 self_cell!(
-    pub struct Surface {
+    struct Attached {
         owner: Window,
         #[covariant]
         dependent: Renderer,
     }
 );
 
+// The pixmap, fonts and window attributes outlive the window and GPU surface, so the surface can
+// be detached in `ApplicationHandler::suspended` and attached again in `resumed` without losing
+// what was drawn or loaded.
+pub struct Surface {
+    pixmap: Pixmap,
+    fonts: FontContext,
+    attributes: WindowAttributes,
+    attached: Option<Attached>,
+    filters: FilterChain,
+}
+
 impl Surface {
-    pub fn window(&self) -> &Window {
-        self.borrow_owner()
+    pub fn new(
+        event_loop: &ActiveEventLoop,
+        attributes: WindowAttributes,
+        fonts: FontContext,
+    ) -> Result<Self> {
+        let window = event_loop.create_window(attributes.clone())?;
+        let win_size = window.inner_size();
+        let pixmap = new_pixmap(win_size.width, win_size.height)?;
+        let attached = Attached::try_new(window, |window| {
+            Renderer::new_with_fonts(window, fonts.clone())
+        })?;
+
+        Ok(Self {
+            pixmap,
+            fonts,
+            attributes,
+            attached: Some(attached),
+            filters: FilterChain::new(),
+        })
     }
 
-    pub fn size(&self) -> (u32, u32) {
-        // self.borrow_owner().inner_size()
-        let surf = &self.borrow_dependent().surface;
-        (surf.width(), surf.height())
+    pub fn window(&self) -> Option<&Window> {
+        self.attached
+            .as_ref()
+            .map(|attached| attached.borrow_owner())
     }
 
-    pub fn request_redraw(&mut self) {
-        self.with_dependent_mut(|win, _rend| win.request_redraw());
+    pub fn is_attached(&self) -> bool {
+        self.attached.is_some()
     }
 
-    pub fn fill(&mut self, colour: tiny_skia::Color) {
-        self.with_dependent_mut(|_win, rend| rend.surface.fill(colour))
+    // Drops the window and everything on the GPU. Drawing into the pixmap still works while
+    // detached, but `update` does nothing and text returns `Error::Detached` until the surface is
+    // attached again.
+    pub fn detach(&mut self) {
+        self.attached = None;
     }
 
-    pub fn background(&mut self, bg: &Pixmap, paint: &PixmapPaint) {
-        self.with_dependent_mut(|_win, rend| {
-            let sx = rend.surface.width() as f32 / bg.width() as f32;
-            let sy = rend.surface.height() as f32 / bg.height() as f32;
-            rend.surface.draw_pixmap(
+    // Creates a new window from the original attributes, less any parent window, whose handle
+    // can be stale by now. If its size changed, the pixmap is resized with its contents kept at
+    // the top left.
+    pub fn attach(&mut self, event_loop: &ActiveEventLoop) -> Result<()> {
+        if self.attached.is_some() {
+            return Ok(());
+        }
+        // SAFETY: no handle is passed
+        self.attributes = unsafe { self.attributes.clone().with_parent_window(None) };
+        let window = event_loop.create_window(self.attributes.clone())?;
+        let win_size = window.inner_size();
+        if (win_size.width, win_size.height) != self.size() {
+            let mut pixmap = new_pixmap(win_size.width, win_size.height)?;
+            pixmap.draw_pixmap(
                 0,
                 0,
-                bg.as_ref(),
-                paint,
-                Transform::from_scale(sx, sy),
+                self.pixmap.as_ref(),
+                &PixmapPaint::default(),
+                Transform::identity(),
                 None,
             );
+            self.pixmap = pixmap;
+        }
+        self.attached = Some(Attached::try_new(window, |window| {
+            Renderer::new_with_fonts(window, self.fonts.clone())
+        })?);
+        Ok(())
+    }
+
+    // Recreates the renderer for the same window, e.g. after the GPU surface was lost. The old
+    // renderer has to go first since it holds the window's GPU surface, so if this fails the
+    // surface is left detached and `attach` can start again with a new window.
+    fn rebuild(&mut self) -> Result<()> {
+        let Some(attached) = self.attached.take() else {
+            return Ok(());
+        };
+        let window = attached.into_owner();
+        let attached = Attached::try_new(window, |window| {
+            Renderer::new_with_fonts(window, self.fonts.clone())
         })
+        .map_err(|e| Error::SurfaceLost(Box::new(e)))?;
+        self.attached = Some(attached);
+        Ok(())
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.pixmap.width(), self.pixmap.height())
+    }
+
+    pub fn pixmap(&self) -> &Pixmap {
+        &self.pixmap
+    }
+
+    pub fn pixmap_mut(&mut self) -> &mut Pixmap {
+        &mut self.pixmap
+    }
+
+    // Run over a copy of the pixmap in `update`, just before it's uploaded.
+    pub fn filters(&self) -> &FilterChain {
+        &self.filters
+    }
+
+    pub fn filters_mut(&mut self) -> &mut FilterChain {
+        &mut self.filters
+    }
+
+    pub fn set_filters(&mut self, filters: FilterChain) {
+        self.filters = filters;
+    }
+
+    pub fn request_redraw(&mut self) {
+        if let Some(window) = self.window() {
+            window.request_redraw();
+        }
+    }
+
+    pub fn fill(&mut self, colour: tiny_skia::Color) {
+        self.pixmap.fill(colour)
+    }
+
+    pub fn background(&mut self, bg: &Pixmap, paint: &PixmapPaint) {
+        let sx = self.pixmap.width() as f32 / bg.width() as f32;
+        let sy = self.pixmap.height() as f32 / bg.height() as f32;
+        self.pixmap.draw_pixmap(
+            0,
+            0,
+            bg.as_ref(),
+            paint,
+            Transform::from_scale(sx, sy),
+            None,
+        );
     }
 
     pub fn blit(
@@ -273,15 +484,59 @@ impl Surface {
         transform: Transform,
         mask: Option<&Mask>,
     ) {
-        self.with_dependent_mut(|_win, rend| rend.blit(x, y, pixmap, paint, transform, mask))
+        self.pixmap
+            .draw_pixmap(x, y, pixmap.as_ref(), paint, transform, mask);
+    }
+
+    pub fn fill_path(
+        &mut self,
+        path: &Path,
+        paint: &Paint,
+        fill_rule: FillRule,
+        transform: Transform,
+        mask: Option<&Mask>,
+    ) {
+        self.pixmap
+            .fill_path(path, paint, fill_rule, transform, mask);
     }
 
+    // An outdated GPU surface is reconfigured and a lost one rebuilt, dropping that frame.
     pub fn update(&mut self) -> Result<()> {
-        self.with_dependent_mut(|_win, rend| rend.update())
+        let Some(attached) = &mut self.attached else {
+            return Ok(());
+        };
+        let pixmap = self.filters.run(&self.pixmap);
+        match attached.with_dependent_mut(|_win, rend| rend.update(pixmap)) {
+            Err(Error::Render(pixels::Error::Surface(SurfaceError::Outdated))) => attached
+                .with_dependent_mut(|win, rend| {
+                    let win_size = win.inner_size();
+                    rend.pixels
+                        .resize_surface(win_size.width, win_size.height)
+                        .map_err(|e| Error::Render(e.into()))
+                }),
+            Err(Error::Render(pixels::Error::Surface(SurfaceError::Lost))) => self.rebuild(),
+            result => result,
+        }
+    }
+
+    pub fn screenshot(&mut self) -> Result<Pixmap> {
+        let attached = self.attached.as_mut().ok_or(Error::Detached)?;
+        attached.with_dependent_mut(|_win, rend| rend.screenshot())
+    }
+
+    pub fn save_screenshot(&mut self, path: impl AsRef<FilePath>) -> Result<()> {
+        let attached = self.attached.as_mut().ok_or(Error::Detached)?;
+        attached.with_dependent_mut(|_win, rend| rend.save_screenshot(path))
+    }
+
+    // Clone this into `new_surface_with_fonts` to share loaded fonts and glyph caches with
+    // another window.
+    pub fn fonts(&self) -> FontContext {
+        self.fonts.clone()
     }
 
     pub fn load_fonts(&mut self, fonts: impl IntoIterator<Item = Source>, update: bool) {
-        self.with_dependent_mut(|_win, rend| rend.load_fonts(fonts, update))
+        self.fonts.load_fonts(fonts, update)
     }
 
     pub fn text_ex(
@@ -292,34 +547,57 @@ impl Surface {
         font_size: f32,
         params: TextParams,
     ) -> Result<()> {
-        self.with_dependent_mut(|_win, rend| rend.text_ex(txt, x, y, font_size, params))
+        let attached = self.attached.as_mut().ok_or(Error::Detached)?;
+        attached.with_dependent_mut(|_win, rend| rend.text_ex(txt, x, y, font_size, params))
     }
 
     pub fn text(&mut self, txt: &str, x: f32, y: f32, font_size: f32, colour: Color) -> Result<()> {
-        self.with_dependent_mut(|_win, rend| rend.text(txt, x, y, font_size, colour))
+        self.text_ex(
+            txt,
+            x,
+            y,
+            font_size,
+            TextParams {
+                colour,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn measure_text(
+        &self,
+        txt: &str,
+        font_size: f32,
+        params: &TextParams,
+    ) -> Result<(f32, f32)> {
+        self.fonts.measure(txt, font_size, params)
     }
 }
 
-pub fn new_window(
-    event_loop: &'_ ActiveEventLoop,
-    title: &str,
-    width: f64,
-    height: f64,
-) -> std::result::Result<Window, OsError> {
+fn new_pixmap(width: u32, height: u32) -> Result<Pixmap> {
+    Pixmap::new(width, height).ok_or(Error::Pixmap { width, height })
+}
+
+fn window_attributes(title: &str, width: f64, height: f64) -> WindowAttributes {
     let size = LogicalSize::new(width, height);
-    #[cfg(not(any(target_os = "android", target_os = "linux")))]
-    let attributes = winit::window::Window::default_attributes()
-        .with_title(title)
-        .with_inner_size(size)
-        .with_min_inner_size(size)
-        .with_visible(true);
-    #[cfg(any(target_os = "android", target_os = "linux"))]
     let attributes = winit::window::Window::default_attributes()
         .with_title(title)
         .with_inner_size(size)
         .with_min_inner_size(size);
+    if cfg!(any(target_os = "android", target_os = "linux")) {
+        attributes
+    } else {
+        attributes.with_visible(true)
+    }
+}
 
-    event_loop.create_window(attributes)
+pub fn new_window(
+    event_loop: &'_ ActiveEventLoop,
+    title: &str,
+    width: f64,
+    height: f64,
+) -> std::result::Result<Window, OsError> {
+    event_loop.create_window(window_attributes(title, width, height))
 }
 
 pub fn new_window_ex(
@@ -329,22 +607,7 @@ pub fn new_window_ex(
     height: f64,
     attr_func: impl FnOnce(WindowAttributes) -> WindowAttributes,
 ) -> std::result::Result<Window, OsError> {
-    let size = LogicalSize::new(width, height);
-    #[cfg(not(any(target_os = "android", target_os = "linux")))]
-    let mut attributes = winit::window::Window::default_attributes()
-        .with_title(title)
-        .with_inner_size(size)
-        .with_min_inner_size(size)
-        .with_visible(true);
-    #[cfg(any(target_os = "android", target_os = "linux"))]
-    let mut attributes = winit::window::Window::default_attributes()
-        .with_title(title)
-        .with_inner_size(size)
-        .with_min_inner_size(size);
-
-    attributes = (attr_func)(attributes);
-
-    event_loop.create_window(attributes)
+    event_loop.create_window((attr_func)(window_attributes(title, width, height)))
 }
 
 pub fn new_surface(
@@ -353,11 +616,11 @@ pub fn new_surface(
     width: f64,
     height: f64,
 ) -> Result<Surface> {
-    let window = new_window(event_loop, title, width, height)?;
-
-    Ok(Surface::new(window, |window| {
-        Renderer::new(window).unwrap()
-    }))
+    Surface::new(
+        event_loop,
+        window_attributes(title, width, height),
+        FontContext::new(),
+    )
 }
 
 pub fn new_surface_ex(
@@ -367,11 +630,40 @@ pub fn new_surface_ex(
     height: f64,
     attributes: impl FnOnce(WindowAttributes) -> WindowAttributes,
 ) -> Result<Surface> {
-    let window = new_window_ex(event_loop, title, width, height, attributes)?;
+    Surface::new(
+        event_loop,
+        (attributes)(window_attributes(title, width, height)),
+        FontContext::new(),
+    )
+}
 
-    Ok(Surface::new(window, |window| {
-        Renderer::new(window).unwrap()
-    }))
+pub fn new_surface_with_fonts(
+    event_loop: &'_ ActiveEventLoop,
+    title: &str,
+    width: f64,
+    height: f64,
+    fonts: &FontContext,
+) -> Result<Surface> {
+    Surface::new(
+        event_loop,
+        window_attributes(title, width, height),
+        fonts.clone(),
+    )
+}
+
+pub fn new_surface_with_fonts_ex(
+    event_loop: &'_ ActiveEventLoop,
+    title: &str,
+    width: f64,
+    height: f64,
+    fonts: &FontContext,
+    attributes: impl FnOnce(WindowAttributes) -> WindowAttributes,
+) -> Result<Surface> {
+    Surface::new(
+        event_loop,
+        (attributes)(window_attributes(title, width, height)),
+        fonts.clone(),
+    )
 }
 
 pub struct TextParams<'a> {
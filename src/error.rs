@@ -0,0 +1,91 @@
+use std::{error::Error as StdError, io, path::PathBuf};
+use thiserror::Error;
+use winit::error::OsError;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error creating window: {0}")]
+    Window(#[from] OsError),
+    // no adapter or device, or the window surface couldn't be created
+    #[error("Error initialising GPU surface: {0}")]
+    Gpu(#[source] pixels::Error),
+    #[error("Surface is detached from its window")]
+    Detached,
+    // the surface is left detached, attaching it again opens a new window
+    #[error("GPU surface lost and couldn't be recreated: {0}")]
+    SurfaceLost(#[source] Box<Error>),
+    #[error("Error initialising {width}x{height} Pixmap")]
+    Pixmap { width: u32, height: u32 },
+    #[error("Error reading {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Error opening archive {}: {source}", path.display())]
+    Archive {
+        path: PathBuf,
+        #[source]
+        source: zip::result::ZipError,
+    },
+    #[error("No font faces found in {0}")]
+    InvalidFont(String),
+    #[error("Font family '{0}' not found")]
+    FamilyNotFound(String),
+    #[error("Unsupported image format: {0}")]
+    UnsupportedImage(String),
+    // corrupt or truncated data, or an image too large to decode
+    #[error("Error decoding {name}: {source}")]
+    Image {
+        name: String,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("Error preparing text: {0}")]
+    PrepareText(#[from] glyphon::PrepareError),
+    #[error("Error rendering text: {0}")]
+    RenderText(#[from] glyphon::RenderError),
+    #[error("Error rendering frame: {0}")]
+    Render(#[from] pixels::Error),
+    #[error("Error reading back screenshot: {0}")]
+    Screenshot(#[source] wgpu::BufferAsyncError),
+    #[error("Error encoding PNG: {0}")]
+    Png(#[from] png::EncodingError),
+    #[error("Palette has {0} colours, the most is 256")]
+    PaletteSize(usize),
+    #[error("{len} indices don't make a {width}x{height} image")]
+    IndexCount { len: usize, width: u32, height: u32 },
+    // a fully transparent pixel needs a transparent entry
+    #[error("Colour {colour:?} at ({x}, {y}) isn't in the palette")]
+    NotInPalette { colour: [u8; 4], x: u32, y: u32 },
+    // an id, index or name that nothing was loaded under
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0} wasn't loaded from a file")]
+    NoPath(String),
+    #[error("Error initialising audio backend: {0}")]
+    AudioBackend(String),
+    // too many sounds, tracks or emitters, or the audio thread has stopped
+    #[error("Audio error: {0}")]
+    Audio(#[source] Box<dyn StdError + Send + Sync>),
+    #[error("Error decoding {name}: {source}")]
+    Sound {
+        name: String,
+        #[source]
+        source: kira::sound::FromFileError,
+    },
+    // malformed RON or TOML, or an unsupported file extension
+    #[error("Error in input bindings: {0}")]
+    Bindings(#[source] Box<dyn StdError + Send + Sync>),
+    #[error("Error watching files: {0}")]
+    Watch(#[from] notify::Error),
+    #[error("Error in {}: {message}", path.display())]
+    Script { path: PathBuf, message: String },
+    #[error("Error encoding GIF: {0}")]
+    Gif(#[from] gif::EncodingError),
+    // the writer thread stopped, or the frames can't be encoded in the format
+    #[error("Error recording: {0}")]
+    Recording(String),
+}
@@ -0,0 +1,526 @@
+// Post-processing run over the whole frame on the CPU, before it's uploaded. Each filter works on
+// a premultiplied pixmap of any size and splits the work across rows with rayon.
+use rayon::prelude::*;
+use tiny_skia::{Color, ColorU8, Pixmap, PremultipliedColorU8};
+
+pub trait Filter: Send + Sync {
+    fn apply(&self, pixmap: &mut Pixmap);
+}
+
+impl<F: Fn(&mut Pixmap) + Send + Sync> Filter for F {
+    fn apply(&self, pixmap: &mut Pixmap) {
+        self(pixmap)
+    }
+}
+
+// Filters applied in order. `run` filters a copy, so effects like blur don't build up on a frame
+// that isn't redrawn from scratch.
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn Filter>>,
+    output: Option<Pixmap>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, filter: impl Filter + 'static) -> Self {
+        self.push(filter);
+        self
+    }
+
+    pub fn push(&mut self, filter: impl Filter + 'static) {
+        self.filters.push(Box::new(filter));
+    }
+
+    pub fn clear(&mut self) {
+        self.filters.clear();
+        self.output = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    pub fn apply(&self, pixmap: &mut Pixmap) {
+        for filter in &self.filters {
+            filter.apply(pixmap);
+        }
+    }
+
+    // Returns `pixmap` itself when there are no filters.
+    pub fn run<'a>(&'a mut self, pixmap: &'a Pixmap) -> &'a Pixmap {
+        if self.filters.is_empty() {
+            return pixmap;
+        }
+        let output = match self.output.take() {
+            Some(mut output)
+                if output.width() == pixmap.width() && output.height() == pixmap.height() =>
+            {
+                output.data_mut().copy_from_slice(pixmap.data());
+                output
+            }
+            _ => pixmap.clone(),
+        };
+        let output = self.output.insert(output);
+        for filter in &self.filters {
+            filter.apply(output);
+        }
+        output
+    }
+}
+
+fn rows(pixmap: &mut Pixmap, f: impl Fn(usize, &mut [PremultipliedColorU8]) + Send + Sync) {
+    let width = pixmap.width() as usize;
+    pixmap
+        .pixels_mut()
+        .par_chunks_exact_mut(width)
+        .enumerate()
+        .for_each(|(y, row)| f(y, row));
+}
+
+// Premultiplied channels in 0.0..=255.0, clamped back into a valid colour.
+fn to_pixel(r: f32, g: f32, b: f32, a: f32) -> PremultipliedColorU8 {
+    let a = a.round().clamp(0.0, 255.0) as u8;
+    let channel = |c: f32| (c.round().clamp(0.0, 255.0) as u8).min(a);
+    PremultipliedColorU8::from_rgba(channel(r), channel(g), channel(b), a)
+        .unwrap_or(PremultipliedColorU8::TRANSPARENT)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GaussianBlur {
+    // standard deviation in pixels
+    pub sigma: f32,
+}
+
+impl GaussianBlur {
+    pub fn new(sigma: f32) -> Self {
+        Self { sigma }
+    }
+
+    fn kernel(&self) -> Vec<f32> {
+        let radius = (self.sigma * 3.0).ceil() as i32;
+        let weights: Vec<f32> = (-radius..=radius)
+            .map(|i| (-(i * i) as f32 / (2.0 * self.sigma * self.sigma)).exp())
+            .collect();
+        let total: f32 = weights.iter().sum();
+        weights.into_iter().map(|w| w / total).collect()
+    }
+}
+
+// Edges are extended, so the frame doesn't darken towards its borders.
+impl Filter for GaussianBlur {
+    fn apply(&self, pixmap: &mut Pixmap) {
+        if self.sigma <= 0.0 {
+            return;
+        }
+        let kernel = self.kernel();
+        let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+        rows(pixmap, |_, row| {
+            let src = row.to_vec();
+            for (x, dst) in row.iter_mut().enumerate() {
+                *dst = convolve(&kernel, |offset| {
+                    src[(x as i32 + offset).clamp(0, width - 1) as usize]
+                });
+            }
+        });
+        let src = pixmap.pixels().to_vec();
+        rows(pixmap, |y, row| {
+            for (x, dst) in row.iter_mut().enumerate() {
+                *dst = convolve(&kernel, |offset| {
+                    let y = (y as i32 + offset).clamp(0, height - 1);
+                    src[(y * width) as usize + x]
+                });
+            }
+        });
+    }
+}
+
+// `sample` is given offsets from the centre of the kernel.
+fn convolve(kernel: &[f32], sample: impl Fn(i32) -> PremultipliedColorU8) -> PremultipliedColorU8 {
+    let radius = (kernel.len() / 2) as i32;
+    let mut sum = [0.0; 4];
+    for (i, weight) in kernel.iter().enumerate() {
+        let pixel = sample(i as i32 - radius);
+        sum[0] += pixel.red() as f32 * weight;
+        sum[1] += pixel.green() as f32 * weight;
+        sum[2] += pixel.blue() as f32 * weight;
+        sum[3] += pixel.alpha() as f32 * weight;
+    }
+    to_pixel(sum[0], sum[1], sum[2], sum[3])
+}
+
+// A 4x5 matrix over straight RGBA in 0.0..=1.0, laid out like SVG's feColorMatrix: a row for each
+// of red, green, blue and alpha, each with a weight per input channel and then an offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColourMatrix(pub [f32; 20]);
+
+impl ColourMatrix {
+    pub fn identity() -> Self {
+        Self::saturation(1.0)
+    }
+
+    pub fn greyscale() -> Self {
+        Self::saturation(0.0)
+    }
+
+    pub fn sepia() -> Self {
+        #[rustfmt::skip]
+        let matrix = [
+            0.393, 0.769, 0.189, 0.0, 0.0,
+            0.349, 0.686, 0.168, 0.0, 0.0,
+            0.272, 0.534, 0.131, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ];
+        Self(matrix)
+    }
+
+    // 0.0 is greyscale, 1.0 leaves colours as they are and higher values oversaturate.
+    pub fn saturation(s: f32) -> Self {
+        #[rustfmt::skip]
+        let matrix = [
+            0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s, 0.0, 0.0,
+            0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s, 0.0, 0.0,
+            0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ];
+        Self(matrix)
+    }
+}
+
+impl Filter for ColourMatrix {
+    fn apply(&self, pixmap: &mut Pixmap) {
+        let m = &self.0;
+        rows(pixmap, |_, row| {
+            for pixel in row {
+                let colour = pixel.demultiply();
+                let input = [
+                    colour.red() as f32 / 255.0,
+                    colour.green() as f32 / 255.0,
+                    colour.blue() as f32 / 255.0,
+                    colour.alpha() as f32 / 255.0,
+                ];
+                let channel = |i: usize| {
+                    let row = &m[i * 5..i * 5 + 5];
+                    let value =
+                        row[..4].iter().zip(input).map(|(w, c)| w * c).sum::<f32>() + row[4];
+                    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+                };
+                *pixel = ColorU8::from_rgba(channel(0), channel(1), channel(2), channel(3))
+                    .premultiply();
+            }
+        });
+    }
+}
+
+// Fades the edges of the frame towards `colour`. Distances are measured from the centre, with the
+// corners at 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vignette {
+    pub colour: Color,
+    // how far out the fade starts
+    pub radius: f32,
+    // how far past `radius` it takes to reach full strength
+    pub softness: f32,
+    pub strength: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            colour: Color::BLACK,
+            radius: 0.5,
+            softness: 0.5,
+            strength: 0.8,
+        }
+    }
+}
+
+impl Filter for Vignette {
+    fn apply(&self, pixmap: &mut Pixmap) {
+        let (cx, cy) = (pixmap.width() as f32 / 2.0, pixmap.height() as f32 / 2.0);
+        let corner = (cx * cx + cy * cy).sqrt();
+        let colour = self.colour.premultiply().to_color_u8();
+        let target = [colour.red(), colour.green(), colour.blue(), colour.alpha()].map(f32::from);
+        rows(pixmap, |y, row| {
+            let dy = y as f32 + 0.5 - cy;
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let dx = x as f32 + 0.5 - cx;
+                let distance = (dx * dx + dy * dy).sqrt() / corner;
+                let t = smoothstep(self.radius, self.radius + self.softness, distance);
+                let amount = t * self.strength.clamp(0.0, 1.0);
+                if amount <= 0.0 {
+                    continue;
+                }
+                let mix = |c: u8, target: f32| c as f32 + (target - c as f32) * amount;
+                *pixel = to_pixel(
+                    mix(pixel.red(), target[0]),
+                    mix(pixel.green(), target[1]),
+                    mix(pixel.blue(), target[2]),
+                    mix(pixel.alpha(), target[3]),
+                );
+            }
+        });
+    }
+}
+
+pub(crate) fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge1 <= edge0 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Darkens every `spacing`th run of `thickness` rows, like the gaps between a CRT's scanlines.
+// Works best when the frame is scaled up from a low resolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scanlines {
+    pub spacing: u32,
+    pub thickness: u32,
+    // 0.0 leaves the lines untouched, 1.0 makes them black
+    pub intensity: f32,
+}
+
+impl Default for Scanlines {
+    fn default() -> Self {
+        Self {
+            spacing: 3,
+            thickness: 1,
+            intensity: 0.4,
+        }
+    }
+}
+
+impl Filter for Scanlines {
+    fn apply(&self, pixmap: &mut Pixmap) {
+        let spacing = self.spacing.max(1) as usize;
+        let keep = 1.0 - self.intensity.clamp(0.0, 1.0);
+        rows(pixmap, |y, row| {
+            if y % spacing >= self.thickness as usize {
+                return;
+            }
+            for pixel in row {
+                let scale = |c: u8| c as f32 * keep;
+                *pixel = to_pixel(
+                    scale(pixel.red()),
+                    scale(pixel.green()),
+                    scale(pixel.blue()),
+                    pixel.alpha() as f32,
+                );
+            }
+        });
+    }
+}
+
+// Splits red and blue apart, pushing red outwards and blue inwards from the centre by up to
+// `offset` pixels at the edges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromaticAberration {
+    pub offset: f32,
+}
+
+impl ChromaticAberration {
+    pub fn new(offset: f32) -> Self {
+        Self { offset }
+    }
+}
+
+impl Filter for ChromaticAberration {
+    fn apply(&self, pixmap: &mut Pixmap) {
+        if self.offset == 0.0 {
+            return;
+        }
+        let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+        let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+        let src = pixmap.pixels().to_vec();
+        let sample = |x: f32, y: f32| {
+            let x = (x.round() as i32).clamp(0, width - 1);
+            let y = (y.round() as i32).clamp(0, height - 1);
+            src[(y * width + x) as usize]
+        };
+        rows(pixmap, |y, row| {
+            let dy = (y as f32 - cy) / cy.max(1.0);
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let dx = (x as f32 - cx) / cx.max(1.0);
+                let (ox, oy) = (dx * self.offset, dy * self.offset);
+                let red = sample(x as f32 - ox, y as f32 - oy);
+                let blue = sample(x as f32 + ox, y as f32 + oy);
+                let alpha = pixel.alpha().max(red.red()).max(blue.blue());
+                *pixel = to_pixel(
+                    red.red() as f32,
+                    pixel.green() as f32,
+                    blue.blue() as f32,
+                    alpha as f32,
+                );
+            }
+        });
+    }
+}
+
+#[rustfmt::skip]
+const BAYER: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+// Quantises each channel to `levels` values with a 4x4 Bayer pattern, for a limited colour look
+// without banding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderedDither {
+    pub levels: u8,
+}
+
+impl OrderedDither {
+    pub fn new(levels: u8) -> Self {
+        Self { levels }
+    }
+}
+
+impl Filter for OrderedDither {
+    fn apply(&self, pixmap: &mut Pixmap) {
+        if self.levels < 2 {
+            return;
+        }
+        let steps = (self.levels - 1) as f32;
+        rows(pixmap, |y, row| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let threshold = (BAYER[y % 4][x % 4] + 0.5) / 16.0;
+                let quantise =
+                    |c: u8| (c as f32 / 255.0 * steps + threshold - 0.5).round() / steps * 255.0;
+                *pixel = to_pixel(
+                    quantise(pixel.red()),
+                    quantise(pixel.green()),
+                    quantise(pixel.blue()),
+                    pixel.alpha() as f32,
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixmap(width: u32, height: u32, colours: &[ColorU8]) -> Pixmap {
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        for (dst, colour) in pixmap.pixels_mut().iter_mut().zip(colours.iter().cycle()) {
+            *dst = colour.premultiply();
+        }
+        pixmap
+    }
+
+    fn gradient() -> Pixmap {
+        let colours: Vec<ColorU8> = (0..16)
+            .map(|i| ColorU8::from_rgba(i * 16, 255 - i * 12, i * 5 + 40, 255))
+            .collect();
+        pixmap(4, 4, &colours)
+    }
+
+    #[test]
+    fn blur_keeps_flat_colour() {
+        let original = pixmap(3, 3, &[ColorU8::from_rgba(200, 100, 50, 255)]);
+        let mut blurred = original.clone();
+        GaussianBlur::new(1.5).apply(&mut blurred);
+        assert_eq!(blurred, original);
+    }
+
+    #[test]
+    fn identity_matrix_changes_nothing() {
+        let original = gradient();
+        let mut filtered = original.clone();
+        ColourMatrix::identity().apply(&mut filtered);
+        assert_eq!(filtered, original);
+    }
+
+    #[test]
+    fn greyscale_equalises_channels() {
+        let mut filtered = gradient();
+        ColourMatrix::greyscale().apply(&mut filtered);
+        for pixel in filtered.pixels() {
+            assert_eq!(pixel.red(), pixel.green());
+            assert_eq!(pixel.green(), pixel.blue());
+        }
+    }
+
+    #[test]
+    fn scanlines_darken_only_their_rows() {
+        let original = pixmap(2, 4, &[ColorU8::from_rgba(200, 100, 50, 255)]);
+        let mut filtered = original.clone();
+        let scanlines = Scanlines {
+            spacing: 2,
+            thickness: 1,
+            intensity: 0.5,
+        };
+        scanlines.apply(&mut filtered);
+        for y in 0..4 {
+            for x in 0..2 {
+                let (before, after) =
+                    (original.pixel(x, y).unwrap(), filtered.pixel(x, y).unwrap());
+                if y % 2 < 1 {
+                    assert_eq!(after.red(), 100);
+                    assert_eq!(after.alpha(), 255);
+                } else {
+                    assert_eq!(after, before);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vignette_darkens_corners() {
+        let mut filtered = pixmap(8, 8, &[ColorU8::from_rgba(255, 255, 255, 255)]);
+        Vignette::default().apply(&mut filtered);
+        let (corner, centre) = (filtered.pixel(0, 0).unwrap(), filtered.pixel(3, 3).unwrap());
+        assert_eq!(centre.red(), 255);
+        assert!(corner.red() < centre.red(), "{}", corner.red());
+    }
+
+    #[test]
+    fn chromatic_aberration_splits_channels() {
+        let (black, white) = (
+            ColorU8::from_rgba(0, 0, 0, 255),
+            ColorU8::from_rgba(255, 255, 255, 255),
+        );
+        let mut colours = [black; 17];
+        colours[12] = white;
+        let mut filtered = pixmap(17, 1, &colours);
+        ChromaticAberration::new(2.0).apply(&mut filtered);
+        let line = filtered.pixel(12, 0).unwrap();
+        // green stays put while red is pushed outwards and blue inwards
+        assert_eq!((line.red(), line.green(), line.blue()), (0, 255, 0));
+        assert_eq!(filtered.pixel(13, 0).unwrap().red(), 255);
+        assert_eq!(filtered.pixel(11, 0).unwrap().blue(), 255);
+    }
+
+    #[test]
+    fn dither_limits_levels() {
+        let mut filtered = gradient();
+        OrderedDither::new(3).apply(&mut filtered);
+        for pixel in filtered.pixels() {
+            for channel in [pixel.red(), pixel.green(), pixel.blue()] {
+                assert!([0, 128, 255].contains(&channel), "{}", channel);
+            }
+        }
+    }
+
+    #[test]
+    fn run_leaves_source_alone() {
+        let original = gradient();
+        let mut chain = FilterChain::new()
+            .with(GaussianBlur::new(1.0))
+            .with(ColourMatrix::sepia());
+        let filtered = chain.run(&original).clone();
+        assert_ne!(filtered, original);
+        assert_eq!(original, gradient());
+    }
+}
@@ -0,0 +1,477 @@
+use crate::{vfs::Vfs, Error, Result, TextParams};
+use glyphon::{
+    cosmic_text::{AttrsOwned, Family, FamilyOwned},
+    fontdb::{Database, Query, Source},
+    Attrs, Buffer, FontSystem, Metrics, SwashCache, TextBounds,
+};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tiny_skia::Pixmap;
+
+pub(crate) struct Fonts {
+    pub(crate) font_sys: Option<FontSystem>,
+    sources: Vec<Source>,
+    pub(crate) glyph_cache: SwashCache,
+    // fonts loaded with `load_font_file`, kept by path so they can be reloaded
+    files: Vec<(PathBuf, Source)>,
+    dirs: Vec<PathBuf>,
+    system_fonts: bool,
+    aliases: HashMap<String, String>,
+    fallback: Vec<String>,
+    // requested family names resolved through the aliases, cleared whenever fonts or aliases change
+    families: HashMap<String, FamilyOwned>,
+    // bumped whenever the font system is rebuilt, since face ids are reused and any glyphs cached
+    // under them go stale
+    pub(crate) generation: u64,
+}
+
+impl Fonts {
+    fn build(&self) -> FontSystem {
+        let sources = self
+            .sources
+            .iter()
+            .chain(self.files.iter().map(|(_, source)| source))
+            .cloned();
+        let mut font_sys = if self.system_fonts {
+            FontSystem::new_with_fonts(sources)
+        } else {
+            // a fixed locale as well, so nothing about the machine changes the output
+            let mut db = Database::new();
+            for source in sources {
+                db.load_font_source(source);
+            }
+            FontSystem::new_with_locale_and_db("en-US".to_string(), db)
+        };
+        for dir in &self.dirs {
+            for source in dir_sources(dir) {
+                font_sys.db_mut().load_font_source(source);
+            }
+        }
+        font_sys
+    }
+
+    pub(crate) fn font_system(&mut self) -> &mut FontSystem {
+        if self.font_sys.is_none() {
+            self.rebuild();
+        }
+        self.font_sys.as_mut().unwrap()
+    }
+
+    fn rebuild(&mut self) {
+        self.font_sys = Some(self.build());
+        self.glyph_cache = SwashCache::new();
+        self.families.clear();
+        self.generation += 1;
+    }
+
+    fn resolve_name(&mut self, name: &str) -> Result<String> {
+        let target = self
+            .aliases
+            .get(name)
+            .map_or(name, |f| f.as_str())
+            .to_string();
+        let found = self.font_system().db().faces().any(|face| {
+            face.families
+                .iter()
+                .any(|(f, _)| f.eq_ignore_ascii_case(&target))
+        });
+        if !found {
+            return Err(Error::FamilyNotFound(name.to_string()));
+        }
+        Ok(target)
+    }
+
+    fn resolve(&mut self, family: Family) -> Result<FamilyOwned> {
+        let Family::Name(name) = family else {
+            return Ok(FamilyOwned::new(family));
+        };
+        if let Some(family) = self.families.get(name) {
+            return Ok(family.clone());
+        }
+        let family = FamilyOwned::Name(self.resolve_name(name)?);
+        self.families.insert(name.to_string(), family.clone());
+        Ok(family)
+    }
+
+    // Splits `txt` into runs, giving each character the first of the requested family and then the
+    // fallback chain that has a glyph for it. Characters none of them cover stay with the requested
+    // family and go through cosmic-text's own fallback.
+    pub(crate) fn spans<'t>(
+        &mut self,
+        txt: &'t str,
+        attrs: Attrs,
+    ) -> Result<Vec<(&'t str, AttrsOwned)>> {
+        let primary = self.resolve(attrs.family)?;
+        if self.fallback.is_empty() {
+            return Ok(vec![(
+                txt,
+                AttrsOwned::new(attrs.family(primary.as_family())),
+            )]);
+        }
+
+        let mut chain = vec![primary];
+        for name in self.fallback.clone() {
+            chain.push(self.resolve(Family::Name(&name))?);
+        }
+        let font_sys = self.font_system();
+        let fonts: Vec<_> = chain
+            .iter()
+            .map(|family| {
+                let id = font_sys.db().query(&Query {
+                    families: &[family.as_family()],
+                    weight: attrs.weight,
+                    stretch: attrs.stretch,
+                    style: attrs.style,
+                })?;
+                font_sys.get_font(id)
+            })
+            .collect();
+
+        let mut spans = Vec::new();
+        let (mut start, mut current) = (0, 0);
+        for (i, c) in txt.char_indices() {
+            // keep spaces, joiners and variation selectors with the run they're in
+            if c.is_whitespace()
+                || c.is_control()
+                || matches!(c, '\u{200d}' | '\u{fe00}'..='\u{fe0f}')
+            {
+                continue;
+            }
+            let index = fonts
+                .iter()
+                .position(|font| {
+                    font.as_ref()
+                        .is_some_and(|font| font.as_swash().charmap().map(c) != 0)
+                })
+                .unwrap_or(0);
+            if index != current {
+                if i > start {
+                    spans.push((&txt[start..i], current));
+                }
+                (start, current) = (i, index);
+            }
+        }
+        spans.push((&txt[start..], current));
+
+        Ok(spans
+            .into_iter()
+            .map(|(s, index)| (s, AttrsOwned::new(attrs.family(chain[index].as_family()))))
+            .collect())
+    }
+
+    // Shapes `txt` for `Surface::text_ex`, wrapping within `size` unless the params set
+    // dimensions.
+    pub(crate) fn layout(
+        &mut self,
+        txt: &str,
+        font_size: f32,
+        params: &TextParams,
+        size: (f32, f32),
+    ) -> Result<Buffer> {
+        let spans = self.spans(txt, params.attrs)?;
+        let font_sys = self.font_system();
+        let line_height = params.line_height.unwrap_or(font_size * 1.5);
+        let mut buf = Buffer::new(font_sys, Metrics::new(font_size, line_height));
+        buf.set_wrap(font_sys, params.wrap);
+        let (width, height) = params.dimensions.unwrap_or(size);
+        buf.set_size(font_sys, width, height);
+        buf.set_rich_text(
+            font_sys,
+            spans.iter().map(|(s, attrs)| (*s, attrs.as_attrs())),
+            params.shaping,
+        );
+        for line in buf.lines.iter_mut() {
+            line.set_align(params.align);
+        }
+        buf.shape_until_scroll(font_sys);
+        Ok(buf)
+    }
+}
+
+// The width of the longest line and the height down to the bottom of the last.
+fn laid_out_size(buf: &Buffer, params: &TextParams) -> (f32, f32) {
+    let line_height = buf.metrics().line_height;
+    let (width, height) = buf.layout_runs().fold((0.0f32, 0.0f32), |(w, h), run| {
+        (w.max(run.line_w), h.max(run.line_top + line_height))
+    });
+    (width * params.scale, height * params.scale)
+}
+
+pub(crate) fn text_bounds(params: &TextParams, x: f32, y: f32, buf: &Buffer) -> TextBounds {
+    params.bounds.unwrap_or_else(|| {
+        let (width, height) = buf.size();
+        TextBounds {
+            left: x as i32,
+            top: y as i32,
+            right: (x + width) as i32,
+            bottom: (y + height) as i32,
+        }
+    })
+}
+
+// Fonts and the CPU glyph cache, shared between every `Surface` created with the same context so
+// font files are only scanned and parsed once. Each window still keeps its own GPU `TextAtlas`,
+// since atlases belong to a single wgpu device.
+#[derive(Clone)]
+pub struct FontContext {
+    inner: Arc<Mutex<Fonts>>,
+}
+
+impl Default for FontContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontContext {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Fonts {
+                font_sys: None,
+                sources: Vec::new(),
+                glyph_cache: SwashCache::new(),
+                files: Vec::new(),
+                dirs: Vec::new(),
+                system_fonts: true,
+                aliases: HashMap::new(),
+                fallback: Vec::new(),
+                families: HashMap::new(),
+                generation: 0,
+            })),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, Fonts> {
+        // a panic while holding the lock can't leave the font system half updated in a way
+        // that matters for drawing, so carry on with whatever is there
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn load_fonts(&self, fonts: impl IntoIterator<Item = Source>, update: bool) {
+        let mut inner = self.lock();
+        inner.sources.extend(fonts);
+        inner.families.clear();
+        if update {
+            inner.rebuild();
+        }
+    }
+
+    // Returns the number of faces loaded, erroring if the data isn't a font.
+    pub fn load_font_bytes(&self, data: impl Into<Vec<u8>>) -> Result<usize> {
+        let source = Source::Binary(Arc::new(data.into()));
+        let mut inner = self.lock();
+        let ids = inner
+            .font_system()
+            .db_mut()
+            .load_font_source(source.clone());
+        if ids.is_empty() {
+            return Err(Error::InvalidFont("font data".to_string()));
+        }
+        inner.sources.push(source);
+        inner.families.clear();
+        Ok(ids.len())
+    }
+
+    pub fn load_font_file(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        let source = read_font(path)?;
+        let mut inner = self.lock();
+        let ids = inner
+            .font_system()
+            .db_mut()
+            .load_font_source(source.clone());
+        if ids.is_empty() {
+            return Err(Error::InvalidFont(path.display().to_string()));
+        }
+        inner.files.push((path.to_path_buf(), source));
+        inner.families.clear();
+        Ok(ids.len())
+    }
+
+    // Reads a font file again and rebuilds the font system around it, as `load_fonts` does with
+    // `update` set. If the file isn't a valid font, say because it's still being written, the
+    // previous version stays loaded.
+    pub fn reload_font_file(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        let source = read_font(path)?;
+        let count = Database::new().load_font_source(source.clone()).len();
+        if count == 0 {
+            return Err(Error::InvalidFont(path.display().to_string()));
+        }
+        let mut inner = self.lock();
+        match inner.files.iter_mut().find(|(p, _)| p == path) {
+            Some((_, old)) => *old = source,
+            None => inner.files.push((path.to_path_buf(), source)),
+        }
+        inner.rebuild();
+        Ok(count)
+    }
+
+    // The files and directories fonts were loaded from, for watching.
+    pub fn font_paths(&self) -> Vec<PathBuf> {
+        let inner = self.lock();
+        inner
+            .files
+            .iter()
+            .map(|(path, _)| path)
+            .chain(&inner.dirs)
+            .cloned()
+            .collect()
+    }
+
+    // Loads every .ttf, .otf, .ttc and .otc file under `dir`, returning the number of faces.
+    pub fn load_font_dir(&self, dir: impl AsRef<Path>) -> Result<usize> {
+        let dir = dir.as_ref();
+        let vfs = Vfs::global();
+        if !vfs.exists(dir) && vfs.list(dir).is_empty() {
+            return Err(Error::Io {
+                path: dir.to_path_buf(),
+                source: io::ErrorKind::NotFound.into(),
+            });
+        }
+        let sources = dir_sources(dir);
+        let mut inner = self.lock();
+        let db = inner.font_system().db_mut();
+        let count = sources
+            .into_iter()
+            .map(|source| db.load_font_source(source).len())
+            .sum();
+        inner.dirs.push(dir.to_path_buf());
+        inner.families.clear();
+        Ok(count)
+    }
+
+    // System fonts are scanned by default. Turning them off makes text render the same on every
+    // machine, using only the fonts loaded explicitly.
+    pub fn set_system_fonts(&self, enabled: bool) {
+        let mut inner = self.lock();
+        if inner.system_fonts != enabled {
+            inner.system_fonts = enabled;
+            inner.font_sys = None;
+        }
+    }
+
+    // Lets `Family::Name(alias)` stand in for `family`, e.g. "ui" or "title".
+    pub fn set_alias(&self, alias: impl Into<String>, family: impl Into<String>) {
+        let mut inner = self.lock();
+        inner.aliases.insert(alias.into(), family.into());
+        inner.families.clear();
+    }
+
+    pub fn remove_alias(&self, alias: &str) {
+        let mut inner = self.lock();
+        inner.aliases.remove(alias);
+        inner.families.clear();
+    }
+
+    // Follows aliases and checks the family is loaded.
+    pub fn resolve_family(&self, name: &str) -> Result<String> {
+        self.lock().resolve_name(name)
+    }
+
+    pub fn has_family(&self, name: &str) -> bool {
+        self.resolve_family(name).is_ok()
+    }
+
+    // Families tried in order for characters the requested family has no glyph for, e.g.
+    // `["Noto Sans CJK JP", "Noto Color Emoji"]`. Aliases work here too.
+    pub fn set_fallback(&self, families: impl IntoIterator<Item = impl Into<String>>) {
+        self.lock().fallback = families.into_iter().map(Into::into).collect();
+    }
+
+    // The size `txt` takes up when drawn with `Surface::text_ex`, for laying things out around it.
+    // Without dimensions in the params, lines only wrap at newlines.
+    pub fn measure(&self, txt: &str, font_size: f32, params: &TextParams) -> Result<(f32, f32)> {
+        let buf = self
+            .lock()
+            .layout(txt, font_size, params, (f32::MAX, f32::MAX))?;
+        Ok(laid_out_size(&buf, params))
+    }
+
+    // Draws text into a pixmap on the CPU, positioned and clipped like `Surface::text_ex`, for
+    // rendering without a window or GPU. Returns the size of the text as laid out for drawing,
+    // which wraps at the pixmap's width without dimensions in the params.
+    pub fn draw_text(
+        &self,
+        pixmap: &mut Pixmap,
+        txt: &str,
+        x: f32,
+        y: f32,
+        font_size: f32,
+        params: &TextParams,
+    ) -> Result<(f32, f32)> {
+        let (width, height) = (pixmap.width(), pixmap.height());
+        let mut inner = self.lock();
+        let buf = inner.layout(txt, font_size, params, (width as f32, height as f32))?;
+        let size = laid_out_size(&buf, params);
+        let bounds = text_bounds(params, x, y, &buf);
+        let (left, top) = (bounds.left.max(0), bounds.top.max(0));
+        let right = bounds.right.min(width as i32);
+        let bottom = bounds.bottom.min(height as i32);
+
+        let Fonts {
+            font_sys: Some(font_sys),
+            glyph_cache,
+            ..
+        } = &mut *inner
+        else {
+            return Ok(size);
+        };
+        let data = pixmap.data_mut();
+        for run in buf.layout_runs() {
+            let line_y = (run.line_y * params.scale).round() as i32;
+            for glyph in run.glyphs {
+                let physical = glyph.physical((x, y), params.scale);
+                let colour = glyph.color_opt.unwrap_or(params.colour);
+                glyph_cache.with_pixels(font_sys, physical.cache_key, colour, |gx, gy, c| {
+                    let (px, py) = (physical.x + gx, line_y + physical.y + gy);
+                    if px < left || px >= right || py < top || py >= bottom {
+                        return;
+                    }
+                    // source over onto premultiplied pixels
+                    let i = (py as usize * width as usize + px as usize) * 4;
+                    let a = c.a() as u32;
+                    for (dst, src) in data[i..i + 3].iter_mut().zip([c.r(), c.g(), c.b()]) {
+                        *dst = ((src as u32 * a + *dst as u32 * (255 - a)) / 255) as u8;
+                    }
+                    data[i + 3] = (a + data[i + 3] as u32 * (255 - a) / 255) as u8;
+                });
+            }
+        }
+        Ok(size)
+    }
+
+    pub fn with_font_system<R>(&self, f: impl FnOnce(&mut FontSystem) -> R) -> R {
+        f(self.lock().font_system())
+    }
+}
+
+fn read_font(path: &Path) -> Result<Source> {
+    Ok(Source::Binary(Arc::new(Vfs::global().read(path)?)))
+}
+
+// Every font file under `dir`. Ones that can't be read are skipped, as fontdb does when it scans
+// a directory itself.
+fn dir_sources(dir: &Path) -> Vec<Source> {
+    let vfs = Vfs::global();
+    vfs.walk(dir)
+        .into_iter()
+        .filter(|path| is_font(path))
+        .filter_map(|path| vfs.read(path).ok())
+        .map(|data| Source::Binary(Arc::new(data)))
+        .collect()
+}
+
+pub(crate) fn is_font(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["ttf", "otf", "ttc", "otc"]
+                .iter()
+                .any(|font| ext.eq_ignore_ascii_case(font))
+        })
+}
@@ -0,0 +1,461 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+use winit::event::{Touch, TouchPhase};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    Tap {
+        x: f32,
+        y: f32,
+    },
+    // The first tap of a double tap is still reported as a `Tap`.
+    DoubleTap {
+        x: f32,
+        y: f32,
+    },
+    LongPress {
+        x: f32,
+        y: f32,
+    },
+    Swipe {
+        start: (f32, f32),
+        end: (f32, f32),
+        direction: SwipeDirection,
+        velocity: f32,
+    },
+    Pan {
+        x: f32,
+        y: f32,
+        dx: f32,
+        dy: f32,
+    },
+    PanEnd {
+        x: f32,
+        y: f32,
+    },
+    // `scale` is relative to the previous pinch event, `total_scale` to the start of the pinch.
+    Pinch {
+        centre: (f32, f32),
+        scale: f32,
+        total_scale: f32,
+    },
+    PinchEnd {
+        total_scale: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+    // distances are in physical pixels, velocities in pixels per second
+    pub tap_slop: f32,
+    pub tap_max_duration: Duration,
+    pub double_tap_interval: Duration,
+    pub double_tap_slop: f32,
+    pub long_press_duration: Duration,
+    pub swipe_min_distance: f32,
+    pub swipe_min_velocity: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            tap_slop: 10.0,
+            tap_max_duration: Duration::from_millis(300),
+            double_tap_interval: Duration::from_millis(300),
+            double_tap_slop: 40.0,
+            long_press_duration: Duration::from_millis(500),
+            swipe_min_distance: 50.0,
+            swipe_min_velocity: 300.0,
+        }
+    }
+}
+
+struct TouchState {
+    start: (f32, f32),
+    start_time: Instant,
+    pos: (f32, f32),
+    moved: bool,
+    long_pressed: bool,
+}
+
+struct PinchState {
+    start_dist: f32,
+    last_dist: f32,
+}
+
+#[derive(Default)]
+pub struct GestureRecognizer {
+    pub config: GestureConfig,
+    touches: BTreeMap<u64, TouchState>,
+    pinch: Option<PinchState>,
+    // set once a second finger goes down, so lifting fingers doesn't also register as taps
+    multi_touch: bool,
+    last_tap: Option<(Instant, (f32, f32))>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn handle_touch(&mut self, touch: &Touch) -> Vec<Gesture> {
+        self.touch(
+            touch.id,
+            touch.phase,
+            (touch.location.x as f32, touch.location.y as f32),
+            Instant::now(),
+        )
+    }
+
+    // Long presses fire while the finger is still down, so this needs calling every frame.
+    pub fn update(&mut self) -> Vec<Gesture> {
+        self.update_at(Instant::now())
+    }
+
+    pub fn touch(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        pos: (f32, f32),
+        now: Instant,
+    ) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+        match phase {
+            TouchPhase::Started => {
+                self.touches.insert(
+                    id,
+                    TouchState {
+                        start: pos,
+                        start_time: now,
+                        pos,
+                        moved: false,
+                        long_pressed: false,
+                    },
+                );
+                if self.touches.len() >= 2 {
+                    self.multi_touch = true;
+                    if self.pinch.is_none() {
+                        let dist = self.pinch_points().map_or(0.0, |(a, b)| distance(a, b));
+                        self.pinch = Some(PinchState {
+                            start_dist: dist,
+                            last_dist: dist,
+                        });
+                    }
+                }
+            }
+            TouchPhase::Moved => {
+                let Some(state) = self.touches.get_mut(&id) else {
+                    return gestures;
+                };
+                let last = state.pos;
+                state.pos = pos;
+                if distance(state.start, pos) > self.config.tap_slop {
+                    state.moved = true;
+                }
+                let moved = state.moved;
+
+                if let (Some((a, b)), Some(pinch)) = (self.pinch_points(), &mut self.pinch) {
+                    let dist = distance(a, b);
+                    if pinch.last_dist > 0.0 && pinch.start_dist > 0.0 {
+                        gestures.push(Gesture::Pinch {
+                            centre: ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0),
+                            scale: dist / pinch.last_dist,
+                            total_scale: dist / pinch.start_dist,
+                        });
+                    }
+                    pinch.last_dist = dist;
+                } else if !self.multi_touch && moved {
+                    gestures.push(Gesture::Pan {
+                        x: pos.0,
+                        y: pos.1,
+                        dx: pos.0 - last.0,
+                        dy: pos.1 - last.1,
+                    });
+                }
+            }
+            TouchPhase::Ended => {
+                let Some(state) = self.touches.remove(&id) else {
+                    return gestures;
+                };
+                if self.multi_touch {
+                    self.end_pinch(&mut gestures);
+                } else {
+                    self.end_single(state, pos, now, &mut gestures);
+                }
+            }
+            TouchPhase::Cancelled => {
+                self.touches.remove(&id);
+                if self.multi_touch {
+                    self.end_pinch(&mut gestures);
+                }
+            }
+        }
+        gestures
+    }
+
+    pub fn update_at(&mut self, now: Instant) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+        if self.multi_touch {
+            return gestures;
+        }
+        for state in self.touches.values_mut() {
+            if !state.moved
+                && !state.long_pressed
+                && now.duration_since(state.start_time) >= self.config.long_press_duration
+            {
+                state.long_pressed = true;
+                gestures.push(Gesture::LongPress {
+                    x: state.pos.0,
+                    y: state.pos.1,
+                });
+            }
+        }
+        gestures
+    }
+
+    pub fn reset(&mut self) {
+        self.touches.clear();
+        self.pinch = None;
+        self.multi_touch = false;
+        self.last_tap = None;
+    }
+
+    fn pinch_points(&self) -> Option<((f32, f32), (f32, f32))> {
+        let mut iter = self.touches.values();
+        Some((iter.next()?.pos, iter.next()?.pos))
+    }
+
+    fn end_pinch(&mut self, gestures: &mut Vec<Gesture>) {
+        if self.touches.len() < 2 {
+            if let Some(pinch) = self.pinch.take() {
+                if pinch.start_dist > 0.0 {
+                    gestures.push(Gesture::PinchEnd {
+                        total_scale: pinch.last_dist / pinch.start_dist,
+                    });
+                }
+            }
+        }
+        if self.touches.is_empty() {
+            self.multi_touch = false;
+        }
+    }
+
+    fn end_single(
+        &mut self,
+        state: TouchState,
+        pos: (f32, f32),
+        now: Instant,
+        gestures: &mut Vec<Gesture>,
+    ) {
+        if state.long_pressed {
+            return;
+        }
+        let duration = now.duration_since(state.start_time);
+        // fast flicks can end far from where they started without a `Moved` in between
+        let moved = state.moved || distance(state.start, pos) > self.config.tap_slop;
+
+        if !moved {
+            if duration > self.config.tap_max_duration {
+                return;
+            }
+            let double = self.last_tap.is_some_and(|(time, at)| {
+                now.duration_since(time) <= self.config.double_tap_interval
+                    && distance(at, pos) <= self.config.double_tap_slop
+            });
+            if double {
+                self.last_tap = None;
+                gestures.push(Gesture::DoubleTap { x: pos.0, y: pos.1 });
+            } else {
+                self.last_tap = Some((now, pos));
+                gestures.push(Gesture::Tap { x: pos.0, y: pos.1 });
+            }
+            return;
+        }
+
+        gestures.push(Gesture::PanEnd { x: pos.0, y: pos.1 });
+
+        let dist = distance(state.start, pos);
+        let velocity = dist / duration.as_secs_f32().max(f32::EPSILON);
+        if dist >= self.config.swipe_min_distance && velocity >= self.config.swipe_min_velocity {
+            let (dx, dy) = (pos.0 - state.start.0, pos.1 - state.start.1);
+            let direction = if dx.abs() >= dy.abs() {
+                if dx < 0.0 {
+                    SwipeDirection::Left
+                } else {
+                    SwipeDirection::Right
+                }
+            } else if dy < 0.0 {
+                SwipeDirection::Up
+            } else {
+                SwipeDirection::Down
+            };
+            gestures.push(Gesture::Swipe {
+                start: state.start,
+                end: pos,
+                direction,
+                velocity,
+            });
+        }
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn tap_and_double_tap() {
+        let mut gestures = GestureRecognizer::default();
+        let t = Instant::now();
+        assert!(gestures
+            .touch(0, TouchPhase::Started, (10.0, 10.0), t)
+            .is_empty());
+        // within the slop, so still a tap
+        assert!(gestures
+            .touch(0, TouchPhase::Moved, (12.0, 11.0), ms(t, 50))
+            .is_empty());
+        assert_eq!(
+            gestures.touch(0, TouchPhase::Ended, (12.0, 11.0), ms(t, 100)),
+            [Gesture::Tap { x: 12.0, y: 11.0 }]
+        );
+
+        gestures.touch(1, TouchPhase::Started, (20.0, 10.0), ms(t, 200));
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Ended, (20.0, 10.0), ms(t, 250)),
+            [Gesture::DoubleTap { x: 20.0, y: 10.0 }]
+        );
+
+        // held too long for a tap, too short for a long press
+        gestures.touch(2, TouchPhase::Started, (0.0, 0.0), ms(t, 1000));
+        assert!(gestures.update_at(ms(t, 1400)).is_empty());
+        assert!(gestures
+            .touch(2, TouchPhase::Ended, (0.0, 0.0), ms(t, 1400))
+            .is_empty());
+    }
+
+    #[test]
+    fn long_press() {
+        let mut gestures = GestureRecognizer::default();
+        let t = Instant::now();
+        gestures.touch(0, TouchPhase::Started, (5.0, 5.0), t);
+        assert!(gestures.update_at(ms(t, 400)).is_empty());
+        assert_eq!(
+            gestures.update_at(ms(t, 500)),
+            [Gesture::LongPress { x: 5.0, y: 5.0 }]
+        );
+        assert!(gestures.update_at(ms(t, 600)).is_empty());
+        assert!(gestures
+            .touch(0, TouchPhase::Ended, (5.0, 5.0), ms(t, 700))
+            .is_empty());
+    }
+
+    #[test]
+    fn swipe() {
+        let mut gestures = GestureRecognizer::default();
+        let t = Instant::now();
+        gestures.touch(0, TouchPhase::Started, (0.0, 0.0), t);
+        assert_eq!(
+            gestures.touch(0, TouchPhase::Moved, (100.0, 0.0), ms(t, 50)),
+            [Gesture::Pan {
+                x: 100.0,
+                y: 0.0,
+                dx: 100.0,
+                dy: 0.0
+            }]
+        );
+        let ended = gestures.touch(0, TouchPhase::Ended, (0.0, 200.0), ms(t, 100));
+        assert_eq!(ended[0], Gesture::PanEnd { x: 0.0, y: 200.0 });
+        let Gesture::Swipe {
+            start,
+            end,
+            direction,
+            velocity,
+        } = ended[1]
+        else {
+            panic!("expected a swipe, got {:?}", ended);
+        };
+        assert_eq!((start, end), ((0.0, 0.0), (0.0, 200.0)));
+        assert_eq!(direction, SwipeDirection::Down);
+        assert!((velocity - 2000.0).abs() < 1.0);
+
+        // the same distance too slowly is only a pan
+        gestures.touch(1, TouchPhase::Started, (200.0, 0.0), ms(t, 1000));
+        gestures.touch(1, TouchPhase::Moved, (100.0, 0.0), ms(t, 1500));
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Ended, (0.0, 0.0), ms(t, 2000)),
+            [Gesture::PanEnd { x: 0.0, y: 0.0 }]
+        );
+    }
+
+    #[test]
+    fn flick_without_moves() {
+        let mut gestures = GestureRecognizer::default();
+        let t = Instant::now();
+        gestures.touch(0, TouchPhase::Started, (300.0, 100.0), t);
+        let ended = gestures.touch(0, TouchPhase::Ended, (100.0, 110.0), ms(t, 80));
+        assert_eq!(ended[0], Gesture::PanEnd { x: 100.0, y: 110.0 });
+        assert!(matches!(
+            ended[1],
+            Gesture::Swipe {
+                direction: SwipeDirection::Left,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn pinch() {
+        let mut gestures = GestureRecognizer::default();
+        let t = Instant::now();
+        gestures.touch(0, TouchPhase::Started, (0.0, 0.0), t);
+        gestures.touch(1, TouchPhase::Started, (100.0, 0.0), ms(t, 10));
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Moved, (200.0, 0.0), ms(t, 50)),
+            [Gesture::Pinch {
+                centre: (100.0, 0.0),
+                scale: 2.0,
+                total_scale: 2.0
+            }]
+        );
+        assert_eq!(
+            gestures.touch(0, TouchPhase::Moved, (50.0, 0.0), ms(t, 100)),
+            [Gesture::Pinch {
+                centre: (125.0, 0.0),
+                scale: 0.75,
+                total_scale: 1.5
+            }]
+        );
+        assert_eq!(
+            gestures.touch(1, TouchPhase::Ended, (200.0, 0.0), ms(t, 150)),
+            [Gesture::PinchEnd { total_scale: 1.5 }]
+        );
+        // lifting the last finger isn't a tap or a pan
+        assert!(gestures
+            .touch(0, TouchPhase::Moved, (80.0, 0.0), ms(t, 160))
+            .is_empty());
+        assert!(gestures
+            .touch(0, TouchPhase::Ended, (80.0, 0.0), ms(t, 200))
+            .is_empty());
+    }
+}
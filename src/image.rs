@@ -0,0 +1,99 @@
+// Decodes the formats art usually arrives in into premultiplied pixmaps, since tiny-skia only
+// reads PNG itself. Everything is converted to 8 bit RGBA first, so greyscale, palette, 16 bit and
+// alpha-less images all come out the same way.
+use crate::{vfs::Vfs, Error, Result};
+use std::path::Path;
+use tiny_skia::{ColorU8, Pixmap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    WebP,
+    Qoi,
+    Bmp,
+    Tga,
+    // only the first frame, `sprite::AnimatedImage` plays the rest
+    Gif,
+}
+
+impl ImageFormat {
+    // From the file's signature. TGA doesn't have one, so it's only recognised by extension.
+    pub fn guess(data: &[u8]) -> Option<Self> {
+        match ::image::guess_format(data).ok()? {
+            ::image::ImageFormat::Png => Some(Self::Png),
+            ::image::ImageFormat::Jpeg => Some(Self::Jpeg),
+            ::image::ImageFormat::WebP => Some(Self::WebP),
+            ::image::ImageFormat::Qoi => Some(Self::Qoi),
+            ::image::ImageFormat::Bmp => Some(Self::Bmp),
+            ::image::ImageFormat::Tga => Some(Self::Tga),
+            ::image::ImageFormat::Gif => Some(Self::Gif),
+            _ => None,
+        }
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "webp" => Some(Self::WebP),
+            "qoi" => Some(Self::Qoi),
+            "bmp" => Some(Self::Bmp),
+            "tga" => Some(Self::Tga),
+            "gif" => Some(Self::Gif),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        Self::from_extension(path.as_ref().extension()?.to_str()?)
+    }
+
+    fn to_image(self) -> ::image::ImageFormat {
+        match self {
+            Self::Png => ::image::ImageFormat::Png,
+            Self::Jpeg => ::image::ImageFormat::Jpeg,
+            Self::WebP => ::image::ImageFormat::WebP,
+            Self::Qoi => ::image::ImageFormat::Qoi,
+            Self::Bmp => ::image::ImageFormat::Bmp,
+            Self::Tga => ::image::ImageFormat::Tga,
+            Self::Gif => ::image::ImageFormat::Gif,
+        }
+    }
+}
+
+// Reads an image through the `Vfs`, going by its contents and then its extension.
+pub fn load(path: impl AsRef<Path>) -> Result<Pixmap> {
+    let path = path.as_ref();
+    let data = Vfs::global().read(path)?;
+    let name = path.display().to_string();
+    let format = ImageFormat::guess(&data)
+        .or_else(|| ImageFormat::from_path(path))
+        .ok_or_else(|| Error::UnsupportedImage(name.clone()))?;
+    decode_named(&data, format, name)
+}
+
+// `format` is guessed from the data if it's `None`.
+pub fn decode(data: &[u8], format: Option<ImageFormat>) -> Result<Pixmap> {
+    let name = "image data".to_string();
+    let format = format
+        .or_else(|| ImageFormat::guess(data))
+        .ok_or_else(|| Error::UnsupportedImage(name.clone()))?;
+    decode_named(data, format, name)
+}
+
+pub(crate) fn decode_named(data: &[u8], format: ImageFormat, name: String) -> Result<Pixmap> {
+    let image = ::image::load_from_memory_with_format(data, format.to_image())
+        .map_err(|source| Error::Image { name, source })?
+        .into_rgba8();
+    from_rgba(image.width(), image.height(), image.as_raw())
+}
+
+// `rgba` is 8 bit RGBA with straight alpha, as image decoders produce.
+pub(crate) fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Pixmap> {
+    let mut pixmap = Pixmap::new(width, height).ok_or(Error::Pixmap { width, height })?;
+    for (dst, src) in pixmap.pixels_mut().iter_mut().zip(rgba.chunks_exact(4)) {
+        *dst = ColorU8::from_rgba(src[0], src[1], src[2], src[3]).premultiply();
+    }
+    Ok(pixmap)
+}
@@ -0,0 +1,337 @@
+use crate::{vfs::Vfs, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+use winit::{
+    event::{ElementState, KeyEvent, MouseButton, Touch, TouchPhase, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+// Touch zones are given as fractions of the window size so bindings survive resizes and
+// different screen resolutions.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TouchZone {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl TouchZone {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Touch(TouchZone),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    #[serde(default)]
+    pub negative: Vec<Binding>,
+    #[serde(default)]
+    pub positive: Vec<Binding>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bindings {
+    #[serde(default)]
+    pub actions: HashMap<String, Vec<Binding>>,
+    #[serde(default)]
+    pub axes: HashMap<String, AxisBinding>,
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind(&mut self, action: &str, binding: Binding) -> &mut Self {
+        self.actions
+            .entry(action.to_owned())
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    pub fn bind_axis(
+        &mut self,
+        axis: &str,
+        negative: impl IntoIterator<Item = Binding>,
+        positive: impl IntoIterator<Item = Binding>,
+    ) -> &mut Self {
+        let entry = self.axes.entry(axis.to_owned()).or_default();
+        entry.negative.extend(negative);
+        entry.positive.extend(positive);
+        self
+    }
+
+    pub fn rebind(&mut self, action: &str, bindings: impl IntoIterator<Item = Binding>) {
+        self.actions
+            .insert(action.to_owned(), bindings.into_iter().collect());
+    }
+
+    pub fn unbind_action(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    pub fn unbind_axis(&mut self, axis: &str) {
+        self.axes.remove(axis);
+    }
+
+    pub fn from_ron(src: &str) -> Result<Self> {
+        ron::from_str(src).map_err(|e| Error::Bindings(e.into()))
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| Error::Bindings(e.into()))
+    }
+
+    pub fn from_toml(src: &str) -> Result<Self> {
+        toml::from_str(src).map_err(|e| Error::Bindings(e.into()))
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| Error::Bindings(e.into()))
+    }
+
+    // The format is picked from the file extension, either `.ron` or `.toml`. Reads go through
+    // the global `Vfs`, so bindings can ship inside a mounted archive.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let src = Vfs::global().read_to_string(path)?;
+        match extension(path)? {
+            Format::Ron => Self::from_ron(&src),
+            Format::Toml => Self::from_toml(&src),
+        }
+    }
+
+    // The `Vfs` is read-only, so unlike `load` this takes a real path on disk, e.g. somewhere in
+    // the user's config directory.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let out = match extension(path)? {
+            Format::Ron => self.to_ron()?,
+            Format::Toml => self.to_toml()?,
+        };
+        fs::write(path, out).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+enum Format {
+    Ron,
+    Toml,
+}
+
+fn extension(path: &Path) -> Result<Format> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("ron") => Ok(Format::Ron),
+        Some("toml") => Ok(Format::Toml),
+        _ => Err(Error::Bindings(
+            format!("Unsupported file format: {}", path.display()).into(),
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Input {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+#[derive(Default)]
+pub struct ActionMap {
+    pub bindings: Bindings,
+    held: HashSet<Input>,
+    // touch id -> normalised position of the finger
+    touches: HashMap<u64, (f32, f32)>,
+    down: HashSet<String>,
+    pressed: HashSet<String>,
+    released: HashSet<String>,
+}
+
+impl ActionMap {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            ..Default::default()
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent, window_size: (u32, u32)) {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code),
+                        state,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => self.set_held(Input::Key(*code), *state == ElementState::Pressed),
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.set_held(Input::Mouse(*button), *state == ElementState::Pressed)
+            }
+            WindowEvent::Touch(touch) => self.handle_touch(touch, window_size),
+            WindowEvent::Focused(false) => {
+                self.held.clear();
+                self.touches.clear();
+            }
+            _ => return,
+        }
+        self.refresh();
+    }
+
+    fn handle_touch(&mut self, touch: &Touch, (width, height): (u32, u32)) {
+        let pos = (
+            touch.location.x as f32 / width.max(1) as f32,
+            touch.location.y as f32 / height.max(1) as f32,
+        );
+        match touch.phase {
+            TouchPhase::Started | TouchPhase::Moved => {
+                self.touches.insert(touch.id, pos);
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+            }
+        }
+    }
+
+    fn set_held(&mut self, input: Input, held: bool) {
+        if held {
+            self.held.insert(input);
+        } else {
+            self.held.remove(&input);
+        }
+    }
+
+    fn is_active(&self, binding: &Binding) -> bool {
+        match binding {
+            Binding::Key(code) => self.held.contains(&Input::Key(*code)),
+            Binding::Mouse(button) => self.held.contains(&Input::Mouse(*button)),
+            Binding::Touch(zone) => self.touches.values().any(|&(x, y)| zone.contains(x, y)),
+        }
+    }
+
+    fn refresh(&mut self) {
+        let now: HashSet<String> = self
+            .bindings
+            .actions
+            .iter()
+            .filter(|(_, bindings)| bindings.iter().any(|b| self.is_active(b)))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in now.difference(&self.down) {
+            self.pressed.insert(name.clone());
+        }
+        for name in self.down.difference(&now) {
+            self.released.insert(name.clone());
+        }
+        self.down = now;
+    }
+
+    // Call once per frame after reading input to reset the pressed and released edges.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    pub fn is_down(&self, action: &str) -> bool {
+        self.down.contains(action)
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.released.contains(action)
+    }
+
+    pub fn axis(&self, axis: &str) -> f32 {
+        match self.bindings.axes.get(axis) {
+            Some(binding) => {
+                let neg = binding.negative.iter().any(|b| self.is_active(b));
+                let pos = binding.positive.iter().any(|b| self.is_active(b));
+                (pos as i32 - neg as i32) as f32
+            }
+            None => 0.0,
+        }
+    }
+
+    pub fn rebind(&mut self, action: &str, bindings: impl IntoIterator<Item = Binding>) {
+        self.bindings.rebind(action, bindings);
+        self.refresh();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings() -> Bindings {
+        let mut bindings = Bindings::new();
+        bindings
+            .bind("jump", Binding::Key(KeyCode::Space))
+            .bind("jump", Binding::Touch(TouchZone::new(0.5, 0.5, 0.5, 0.5)))
+            .bind("fire", Binding::Mouse(MouseButton::Left))
+            .bind_axis(
+                "horizontal",
+                [Binding::Key(KeyCode::ArrowLeft)],
+                [Binding::Key(KeyCode::ArrowRight)],
+            );
+        bindings
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let bindings = bindings();
+        let src = bindings.to_ron().unwrap();
+        assert_eq!(Bindings::from_ron(&src).unwrap(), bindings);
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let bindings = bindings();
+        let src = bindings.to_toml().unwrap();
+        assert_eq!(Bindings::from_toml(&src).unwrap(), bindings);
+    }
+
+    #[test]
+    fn unbind_shared_name() {
+        let mut bindings = bindings();
+        bindings.bind("horizontal", Binding::Key(KeyCode::KeyH));
+
+        bindings.unbind_action("horizontal");
+        assert!(!bindings.actions.contains_key("horizontal"));
+        assert!(bindings.axes.contains_key("horizontal"));
+
+        bindings.bind("horizontal", Binding::Key(KeyCode::KeyH));
+        bindings.unbind_axis("horizontal");
+        assert!(bindings.actions.contains_key("horizontal"));
+        assert!(!bindings.axes.contains_key("horizontal"));
+    }
+}
@@ -1,6 +1,32 @@
+mod capi;
 mod draw;
+mod error;
+mod font;
+pub mod audio;
+pub mod filter;
+pub mod gesture;
+pub mod image;
+pub mod input;
+pub mod light;
+pub mod music;
+pub mod palette;
+pub mod particles;
+#[cfg(feature = "python")]
+mod python;
+pub mod recorder;
+pub mod scene;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod sprite;
+pub mod texture;
+pub mod transition;
+pub mod tween;
+pub mod vfs;
+pub mod watch;
 pub use anyhow;
 pub use draw::*;
+pub use error::*;
+pub use font::*;
 pub use glyphon;
 pub use tiny_skia;
 pub use winit;
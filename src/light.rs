@@ -0,0 +1,364 @@
+use crate::{filter::smoothstep, Error, Result, Surface};
+use std::f32::consts::{PI, TAU};
+use tiny_skia::{
+    BlendMode, Color, FillRule, GradientStop, Mask, Paint, Path, PathBuilder, Pixmap, PixmapPaint,
+    Point, RadialGradient, SpreadMode, Transform,
+};
+
+// Gradient stops used to approximate a light's falloff curve.
+const FALLOFF_STOPS: usize = 8;
+
+// Brightness from the light's centre at 0.0 to its radius at 1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Falloff {
+    Linear,
+    #[default]
+    Quadratic,
+    Smooth,
+}
+
+impl Falloff {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Falloff::Linear => 1.0 - t,
+            Falloff::Quadratic => (1.0 - t) * (1.0 - t),
+            Falloff::Smooth => 1.0 - smoothstep(0.0, 1.0, t),
+        }
+    }
+}
+
+// Restricts a light to a wedge, for torches carried by someone and lamps on walls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cone {
+    // radians, 0.0 points right and positive angles turn clockwise on screen
+    pub direction: f32,
+    // the full width of the wedge, in radians
+    pub angle: f32,
+    // how far in from each side the edge fades, in radians
+    pub softness: f32,
+}
+
+// Randomly dims a light by up to `amount` of its intensity, moving smoothly between `speed` new
+// values a second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flicker {
+    pub amount: f32,
+    pub speed: f32,
+}
+
+impl Flicker {
+    fn factor(&self, time: f32, seed: u32) -> f32 {
+        1.0 - self.amount.clamp(0.0, 1.0) * noise(time * self.speed, seed)
+    }
+}
+
+// Not `PartialEq`, since every light gets its own random flicker seed.
+#[derive(Debug, Clone)]
+pub struct Light {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub colour: Color,
+    // scales the colour, which saturates at 1.0
+    pub intensity: f32,
+    pub falloff: Falloff,
+    pub cone: Option<Cone>,
+    pub flicker: Option<Flicker>,
+    // whether the `Lighting`'s occluders block this light
+    pub shadows: bool,
+    // so lights flickering at the same speed don't flicker together
+    seed: u32,
+}
+
+impl Light {
+    pub fn radial(x: f32, y: f32, radius: f32, colour: Color) -> Self {
+        Self {
+            x,
+            y,
+            radius,
+            colour,
+            intensity: 1.0,
+            falloff: Falloff::default(),
+            cone: None,
+            flicker: None,
+            shadows: true,
+            seed: fastrand::u32(..),
+        }
+    }
+
+    pub fn cone(x: f32, y: f32, radius: f32, colour: Color, direction: f32, angle: f32) -> Self {
+        Self {
+            cone: Some(Cone {
+                direction,
+                angle,
+                softness: angle * 0.25,
+            }),
+            ..Self::radial(x, y, radius, colour)
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    pub fn with_flicker(mut self, amount: f32, speed: f32) -> Self {
+        self.flicker = Some(Flicker { amount, speed });
+        self
+    }
+
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+}
+
+// A polygon that blocks light, e.g. a wall or pillar. Its points go round the outline in either
+// direction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occluder {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Occluder {
+    pub fn new(points: impl IntoIterator<Item = (f32, f32)>) -> Self {
+        Self {
+            points: points.into_iter().collect(),
+        }
+    }
+
+    pub fn rect(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self::new([
+            (x, y),
+            (x + width, y),
+            (x + width, y + height),
+            (x, y + height),
+        ])
+    }
+}
+
+// Lights accumulated over an ambient colour into a light map, which is multiplied over the frame
+// so unlit areas fall to the ambient colour. Positions are in surface pixels. Draw the scene, then
+// call `draw` before `Surface::update`, and before anything like a HUD that shouldn't be lit.
+pub struct Lighting {
+    pub ambient: Color,
+    pub lights: Vec<Light>,
+    pub occluders: Vec<Occluder>,
+    time: f32,
+    light_map: Option<Pixmap>,
+    mask: Option<Mask>,
+}
+
+impl Lighting {
+    pub fn new(ambient: Color) -> Self {
+        Self {
+            ambient,
+            lights: Vec::new(),
+            occluders: Vec::new(),
+            time: 0.0,
+            light_map: None,
+            mask: None,
+        }
+    }
+
+    // Advances flickering.
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    // The light map from the last `render` or `draw`.
+    pub fn light_map(&self) -> Option<&Pixmap> {
+        self.light_map.as_ref()
+    }
+
+    pub fn render(&mut self, width: u32, height: u32) -> Result<&Pixmap> {
+        let mut light_map = match self.light_map.take() {
+            Some(map) if map.width() == width && map.height() == height => map,
+            _ => Pixmap::new(width, height).ok_or(Error::Pixmap { width, height })?,
+        };
+        let mut mask = match self.mask.take() {
+            Some(mask) if mask.width() == width && mask.height() == height => mask,
+            _ => Mask::new(width, height).ok_or(Error::Pixmap { width, height })?,
+        };
+
+        let mut ambient = self.ambient;
+        ambient.set_alpha(1.0);
+        light_map.fill(ambient);
+        for light in &self.lights {
+            self.draw_light(&mut light_map, &mut mask, light);
+        }
+
+        self.mask = Some(mask);
+        Ok(self.light_map.insert(light_map))
+    }
+
+    pub fn draw(&mut self, surface: &mut Surface) -> Result<()> {
+        let (width, height) = surface.size();
+        let light_map = self.render(width, height)?;
+        let paint = PixmapPaint {
+            blend_mode: BlendMode::Multiply,
+            ..Default::default()
+        };
+        surface.pixmap_mut().draw_pixmap(
+            0,
+            0,
+            light_map.as_ref(),
+            &paint,
+            Transform::identity(),
+            None,
+        );
+        Ok(())
+    }
+
+    fn draw_light(&self, light_map: &mut Pixmap, mask: &mut Mask, light: &Light) {
+        let flicker = light
+            .flicker
+            .map_or(1.0, |flicker| flicker.factor(self.time, light.seed));
+        let intensity = light.intensity * flicker;
+        if intensity <= 0.0 || light.radius <= 0.0 {
+            return;
+        }
+
+        let stops = (0..=FALLOFF_STOPS)
+            .map(|i| {
+                let t = i as f32 / FALLOFF_STOPS as f32;
+                let brightness = light.falloff.apply(t) * intensity;
+                let channel = |c: f32| (c * brightness).clamp(0.0, 1.0);
+                let colour = Color::from_rgba(
+                    channel(light.colour.red()),
+                    channel(light.colour.green()),
+                    channel(light.colour.blue()),
+                    1.0,
+                )
+                .unwrap_or(Color::BLACK);
+                GradientStop::new(t, colour)
+            })
+            .collect();
+        let centre = Point::from_xy(light.x, light.y);
+        let Some(shader) = RadialGradient::new(
+            centre,
+            centre,
+            light.radius,
+            stops,
+            SpreadMode::Pad,
+            Transform::identity(),
+        ) else {
+            return;
+        };
+        let Some(circle) = PathBuilder::from_circle(light.x, light.y, light.radius) else {
+            return;
+        };
+
+        let shadows = match light.shadows {
+            true => shadow_path(light, &self.occluders),
+            false => None,
+        };
+        let masked = shadows.is_some() || light.cone.is_some();
+        if let Some(shadows) = shadows {
+            mask.clear();
+            mask.fill_path(&shadows, FillRule::Winding, true, Transform::identity());
+            mask.invert();
+        } else if masked {
+            mask.data_mut().fill(255);
+        }
+        if let Some(cone) = light.cone {
+            apply_cone(mask, light, cone);
+        }
+
+        let paint = Paint {
+            shader,
+            blend_mode: BlendMode::Plus,
+            anti_alias: true,
+            ..Default::default()
+        };
+        light_map.fill_path(
+            &circle,
+            &paint,
+            FillRule::Winding,
+            Transform::identity(),
+            masked.then_some(&*mask),
+        );
+    }
+}
+
+// A shape covering everything the occluders hide from the light, out past its radius. Each edge
+// casts a quad away from the light, with an extra point in the middle so the far side stays
+// outside the radius even for edges right next to the light.
+fn shadow_path(light: &Light, occluders: &[Occluder]) -> Option<Path> {
+    let far = light.radius * 2.0;
+    let away = |(x, y): (f32, f32), distance: f32| {
+        let (dx, dy) = (x - light.x, y - light.y);
+        let len = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+        (light.x + dx / len * distance, light.y + dy / len * distance)
+    };
+    let dist = |(x, y): (f32, f32)| ((x - light.x).powi(2) + (y - light.y).powi(2)).sqrt();
+
+    let mut pb = PathBuilder::new();
+    for occluder in occluders {
+        let points = &occluder.points;
+        for (i, &a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            let cross = (a.0 - light.x) * (b.1 - light.y) - (a.1 - light.y) * (b.0 - light.x);
+            if cross.abs() <= f32::EPSILON {
+                continue;
+            }
+            // every quad wound the same way, so overlapping ones don't cancel out
+            let (a, b) = if cross > 0.0 { (a, b) } else { (b, a) };
+            let a_far = away(a, dist(a) + far);
+            let b_far = away(b, dist(b) + far);
+            let mid = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+            let mid_far = away(mid, dist(a).max(dist(b)) + far * 2.0);
+            pb.move_to(a.0, a.1);
+            pb.line_to(b.0, b.1);
+            pb.line_to(b_far.0, b_far.1);
+            pb.line_to(mid_far.0, mid_far.1);
+            pb.line_to(a_far.0, a_far.1);
+            pb.close();
+        }
+    }
+    pb.finish()
+}
+
+// Scales the mask by the cone's coverage within the light's radius.
+fn apply_cone(mask: &mut Mask, light: &Light, cone: Cone) {
+    let (width, height) = (mask.width() as i32, mask.height() as i32);
+    let half = cone.angle / 2.0;
+    let x0 = ((light.x - light.radius).floor() as i32).clamp(0, width);
+    let x1 = ((light.x + light.radius).ceil() as i32).clamp(0, width);
+    let y0 = ((light.y - light.radius).floor() as i32).clamp(0, height);
+    let y1 = ((light.y + light.radius).ceil() as i32).clamp(0, height);
+    let data = mask.data_mut();
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let angle = (y as f32 + 0.5 - light.y).atan2(x as f32 + 0.5 - light.x);
+            let diff = ((angle - cone.direction + PI).rem_euclid(TAU) - PI).abs();
+            let coverage = 1.0 - smoothstep(half - cone.softness, half, diff);
+            let value = &mut data[(y * width + x) as usize];
+            *value = (*value as f32 * coverage).round() as u8;
+        }
+    }
+}
+
+// Smooth value noise in 0.0..=1.0.
+fn noise(t: f32, seed: u32) -> f32 {
+    let i = t.floor();
+    let f = t - i;
+    let (a, b) = (hash(i as i32, seed), hash(i as i32 + 1, seed));
+    a + (b - a) * f * f * (3.0 - 2.0 * f)
+}
+
+fn hash(i: i32, seed: u32) -> f32 {
+    let mut h = (i as u32).wrapping_mul(0x9e37_79b1) ^ seed;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h as f32 / u32::MAX as f32
+}
@@ -0,0 +1,345 @@
+use crate::{
+    audio::{audio_error, tween, Audio, Bus},
+    vfs::Vfs,
+    Error, Result,
+};
+use kira::{
+    manager::backend::Backend,
+    sound::{
+        streaming::{StreamingSoundData, StreamingSoundHandle},
+        FromFileError, PlaybackState,
+    },
+    tween::Tween,
+    Volume,
+};
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+#[derive(Debug, Clone)]
+pub enum TrackSource {
+    File(PathBuf),
+    Bytes(Arc<[u8]>),
+}
+
+// Loop points are in seconds. A track with loop points repeats until another track is played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopPoints {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub source: TrackSource,
+    pub loop_points: Option<LoopPoints>,
+    pub volume: f64,
+}
+
+impl Track {
+    pub fn from_file(path: impl AsRef<Path>) -> Self {
+        Self {
+            source: TrackSource::File(path.as_ref().to_owned()),
+            loop_points: None,
+            volume: 1.0,
+        }
+    }
+
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self {
+            source: TrackSource::Bytes(bytes.into()),
+            loop_points: None,
+            volume: 1.0,
+        }
+    }
+
+    pub fn with_loop(mut self, start: f64, end: Option<f64>) -> Self {
+        self.loop_points = Some(LoopPoints { start, end });
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f64) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    fn load(&self) -> Result<StreamingSoundData<FromFileError>> {
+        let data = match &self.source {
+            // streamed from disk when it's there, otherwise from memory
+            TrackSource::File(path) => match Vfs::global().real_path(path) {
+                Some(real) => StreamingSoundData::from_file(real),
+                None => StreamingSoundData::from_cursor(Cursor::new(Vfs::global().read(path)?)),
+            }
+            .map_err(|source| Error::Sound {
+                name: path.display().to_string(),
+                source,
+            })?,
+            TrackSource::Bytes(bytes) => {
+                StreamingSoundData::from_cursor(Cursor::new(bytes.clone())).map_err(|source| {
+                    Error::Sound {
+                        name: "track data".to_string(),
+                        source,
+                    }
+                })?
+            }
+        };
+        Ok(match self.loop_points {
+            Some(LoopPoints {
+                start,
+                end: Some(end),
+            }) => data.loop_region(start..end),
+            Some(LoopPoints { start, end: None }) => data.loop_region(start..),
+            None => data,
+        })
+    }
+}
+
+struct Playing {
+    handle: StreamingSoundHandle<FromFileError>,
+    duration: Duration,
+    looping: bool,
+    // set once the last track of a non-repeating playlist is left to play out
+    ending: bool,
+}
+
+pub struct MusicPlayer {
+    playlist: Vec<Track>,
+    order: Vec<usize>,
+    position: usize,
+    shuffle: bool,
+    pub repeat: bool,
+    pub crossfade: Tween,
+    bus: Bus,
+    current: Option<Playing>,
+    fading: Vec<StreamingSoundHandle<FromFileError>>,
+}
+
+impl MusicPlayer {
+    pub fn new(playlist: impl IntoIterator<Item = Track>) -> Self {
+        let playlist: Vec<Track> = playlist.into_iter().collect();
+        Self {
+            order: (0..playlist.len()).collect(),
+            playlist,
+            position: 0,
+            shuffle: false,
+            repeat: true,
+            crossfade: tween(Duration::from_secs(2)),
+            bus: Bus::Music,
+            current: None,
+            fading: Vec::new(),
+        }
+    }
+
+    pub fn with_bus(mut self, bus: Bus) -> Self {
+        self.bus = bus;
+        self
+    }
+
+    pub fn playlist(&self) -> &[Track] {
+        &self.playlist
+    }
+
+    pub fn push(&mut self, track: Track) {
+        self.order.push(self.playlist.len());
+        self.playlist.push(track);
+    }
+
+    // Empties the playlist and fades out the current track.
+    pub fn clear(&mut self) {
+        self.stop(self.crossfade.duration);
+        self.playlist.clear();
+        self.order.clear();
+        self.position = 0;
+    }
+
+    // Index into `playlist()` of the current track.
+    pub fn current(&self) -> Option<usize> {
+        self.order.get(self.position).copied()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|playing| playing.handle.state() != PlaybackState::Stopped)
+    }
+
+    pub fn shuffle(&self) -> bool {
+        self.shuffle
+    }
+
+    // Reshuffles the remaining order, keeping the current track where it is.
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle = shuffle;
+        let current = self.current();
+        self.order = (0..self.playlist.len()).collect();
+        if shuffle {
+            fastrand::shuffle(&mut self.order);
+        }
+        if let Some(current) = current {
+            self.position = self.order.iter().position(|&i| i == current).unwrap_or(0);
+        }
+    }
+
+    // Starts the current track, or resumes it if it's paused. Does nothing if it's already playing.
+    pub fn play<B: Backend>(&mut self, audio: &mut Audio<B>) -> Result<()> {
+        if let Some(playing) = &mut self.current {
+            match playing.handle.state() {
+                PlaybackState::Stopped => {}
+                PlaybackState::Paused | PlaybackState::Pausing => {
+                    playing.handle.resume(self.crossfade);
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            }
+        }
+        self.crossfade_to(audio, self.position, self.crossfade)
+    }
+
+    pub fn play_track<B: Backend>(&mut self, audio: &mut Audio<B>, index: usize) -> Result<()> {
+        let position = self
+            .order
+            .iter()
+            .position(|&i| i == index)
+            .ok_or_else(|| Error::NotFound(format!("Track {}", index)))?;
+        self.crossfade_to(audio, position, self.crossfade)
+    }
+
+    pub fn next<B: Backend>(&mut self, audio: &mut Audio<B>) -> Result<()> {
+        self.next_ex(audio, self.crossfade)
+    }
+
+    fn next_ex<B: Backend>(&mut self, audio: &mut Audio<B>, fade: Tween) -> Result<()> {
+        if self.order.is_empty() {
+            return Ok(());
+        }
+        match self.next_position() {
+            Some(position) => self.crossfade_to(audio, position, fade),
+            None => {
+                self.stop(fade.duration);
+                Ok(())
+            }
+        }
+    }
+
+    // None once the last track is reached and the playlist doesn't repeat.
+    fn next_position(&mut self) -> Option<usize> {
+        let position = self.position + 1;
+        if position < self.order.len() {
+            return Some(position);
+        }
+        if !self.repeat {
+            return None;
+        }
+        if self.shuffle {
+            fastrand::shuffle(&mut self.order);
+        }
+        Some(0)
+    }
+
+    pub fn previous<B: Backend>(&mut self, audio: &mut Audio<B>) -> Result<()> {
+        if self.order.is_empty() {
+            return Ok(());
+        }
+        let position = match self.position {
+            0 => self.order.len() - 1,
+            p => p - 1,
+        };
+        self.crossfade_to(audio, position, self.crossfade)
+    }
+
+    // Fades the current track out while `fade` brings the track at `position` in the play order up.
+    pub fn crossfade_to<B: Backend>(
+        &mut self,
+        audio: &mut Audio<B>,
+        position: usize,
+        fade: Tween,
+    ) -> Result<()> {
+        let track = self
+            .order
+            .get(position)
+            .and_then(|&i| self.playlist.get(i))
+            .ok_or_else(|| Error::NotFound(format!("Playlist position {}", position)))?;
+
+        let mut data = track.load()?.volume(Volume::Amplitude(0.0));
+        if let Some(bus) = audio.track(self.bus) {
+            data = data.output_destination(bus);
+        }
+        let duration = data.duration();
+        let looping = track.loop_points.is_some();
+        let volume = track.volume;
+
+        let mut handle = audio.manager().play(data).map_err(audio_error)?;
+        handle.set_volume(Volume::Amplitude(volume), fade);
+
+        if let Some(mut old) = self.current.take() {
+            old.handle.stop(fade);
+            self.fading.push(old.handle);
+        }
+        self.position = position;
+        self.current = Some(Playing {
+            handle,
+            duration,
+            looping,
+            ending: false,
+        });
+        Ok(())
+    }
+
+    pub fn stop(&mut self, fade: Duration) {
+        if let Some(mut playing) = self.current.take() {
+            playing.handle.stop(tween(fade));
+            self.fading.push(playing.handle);
+        }
+    }
+
+    pub fn pause(&mut self, fade: Duration) {
+        if let Some(playing) = &mut self.current {
+            playing.handle.pause(tween(fade));
+        }
+    }
+
+    pub fn resume(&mut self, fade: Duration) {
+        if let Some(playing) = &mut self.current {
+            playing.handle.resume(tween(fade));
+        }
+    }
+
+    // Call once per frame. Starts crossfading into the next track as the current one nears its end.
+    // Tracks shorter than two crossfades fade over half their length instead. Without `repeat` the
+    // last track plays out to its end. A track that fails to load is skipped on the next call.
+    pub fn update<B: Backend>(&mut self, audio: &mut Audio<B>) -> Result<()> {
+        self.fading
+            .retain(|handle| handle.state() != PlaybackState::Stopped);
+
+        let Some(playing) = &mut self.current else {
+            return Ok(());
+        };
+        if playing.looping || playing.ending || playing.handle.state() != PlaybackState::Playing {
+            if playing.handle.state() == PlaybackState::Stopped {
+                self.current = None;
+            }
+            return Ok(());
+        }
+        let fade = Tween {
+            duration: self.crossfade.duration.min(playing.duration / 2),
+            ..self.crossfade
+        };
+        let remaining = playing.duration.as_secs_f64() - playing.handle.position();
+        if self.order.is_empty() || remaining > fade.duration.as_secs_f64() {
+            return Ok(());
+        }
+        let Some(position) = self.next_position() else {
+            if let Some(playing) = &mut self.current {
+                playing.ending = true;
+            }
+            return Ok(());
+        };
+        // the current track keeps playing either way, so a broken track only costs its own slot
+        self.crossfade_to(audio, position, fade).inspect_err(|_| {
+            self.position = position;
+        })
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{image, tween::Lerp, Error, Result, Surface};
+use std::{collections::HashMap, ops::RangeInclusive, path::Path};
+use tiny_skia::{Color, Pixmap, PremultipliedColorU8};
+
+pub const MAX_COLOURS: usize = 256;
+
+// Up to 256 colours that indexed images are drawn with. Swapping, cycling or fading the palette
+// recolours everything drawn with it without touching the images.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    colours: Vec<Color>,
+}
+
+impl Palette {
+    pub fn new(colours: impl IntoIterator<Item = Color>) -> Result<Self> {
+        let colours: Vec<Color> = colours.into_iter().collect();
+        if colours.len() > MAX_COLOURS {
+            return Err(Error::PaletteSize(colours.len()));
+        }
+        Ok(Self { colours })
+    }
+
+    pub fn len(&self) -> usize {
+        self.colours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colours.is_empty()
+    }
+
+    pub fn colours(&self) -> &[Color] {
+        &self.colours
+    }
+
+    pub fn get(&self, index: u8) -> Option<Color> {
+        self.colours.get(index as usize).copied()
+    }
+
+    // Grows the palette with transparent entries if `index` is past the end.
+    pub fn set(&mut self, index: u8, colour: Color) {
+        let index = index as usize;
+        if index >= self.colours.len() {
+            self.colours.resize(index + 1, Color::TRANSPARENT);
+        }
+        self.colours[index] = colour;
+    }
+
+    // A copy with some entries replaced, e.g. a team's colours over the ramp its units are
+    // drawn in.
+    pub fn with_swaps(&self, swaps: impl IntoIterator<Item = (u8, Color)>) -> Self {
+        let mut palette = self.clone();
+        for (index, colour) in swaps {
+            palette.set(index, colour);
+        }
+        palette
+    }
+
+    // Rotates the entries in `range` by `steps`, moving each colour to a higher index for
+    // positive steps. Entries past the end of the palette are left out.
+    pub fn cycle(&mut self, range: RangeInclusive<u8>, steps: i32) {
+        let start = *range.start() as usize;
+        let end = (*range.end() as usize).min(self.colours.len().saturating_sub(1));
+        if start >= end {
+            return;
+        }
+        let entries = &mut self.colours[start..=end];
+        let len = entries.len() as i32;
+        entries.rotate_right(steps.rem_euclid(len) as usize);
+    }
+
+    // Every entry moved `t` of the way to `target`, e.g. black to fade out. Alpha is kept, so
+    // transparent entries stay transparent.
+    pub fn faded(&self, target: Color, t: f32) -> Self {
+        let colours = self
+            .colours
+            .iter()
+            .map(|colour| {
+                let mut faded = colour.lerp(target, t);
+                faded.set_alpha(colour.alpha());
+                faded
+            })
+            .collect();
+        Self { colours }
+    }
+
+    // Entries past the end of the palette are transparent.
+    fn table(&self) -> [PremultipliedColorU8; MAX_COLOURS] {
+        let mut table = [PremultipliedColorU8::TRANSPARENT; MAX_COLOURS];
+        for (entry, colour) in table.iter_mut().zip(&self.colours) {
+            *entry = colour.to_color_u8().premultiply();
+        }
+        table
+    }
+}
+
+// Cycles a range of palette entries at a steady rate, for water, lava and conveyor belts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteCycle {
+    pub range: RangeInclusive<u8>,
+    // steps per second, negative to cycle the other way
+    pub rate: f32,
+    elapsed: f32,
+}
+
+impl PaletteCycle {
+    pub fn new(range: RangeInclusive<u8>, rate: f32) -> Self {
+        Self {
+            range,
+            rate,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    pub fn steps(&self) -> i32 {
+        (self.elapsed * self.rate).floor() as i32
+    }
+
+    // A copy of `palette` cycled by every step so far. Pass the same uncycled palette each frame
+    // rather than the last result, which would add the steps up again.
+    pub fn apply(&self, palette: &Palette) -> Palette {
+        let mut cycled = palette.clone();
+        cycled.cycle(self.range.clone(), self.steps());
+        cycled
+    }
+}
+
+// Maps indices to other indices as an image is blitted, so sprites sharing a layer can use
+// different ramps of the palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Remap([u8; MAX_COLOURS]);
+
+impl Default for Remap {
+    fn default() -> Self {
+        let mut map = [0; MAX_COLOURS];
+        for (i, index) in map.iter_mut().enumerate() {
+            *index = i as u8;
+        }
+        Self(map)
+    }
+}
+
+impl Remap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, from: u8, to: u8) -> Self {
+        self.0[from as usize] = to;
+        self
+    }
+
+    pub fn get(&self, index: u8) -> u8 {
+        self.0[index as usize]
+    }
+}
+
+// A grid of palette indices. Used both for sprites and for the layer they're blitted into, which
+// is resolved through a palette into the surface when it's drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedImage {
+    width: u32,
+    height: u32,
+    indices: Vec<u8>,
+    // pixels with this index aren't drawn
+    pub transparent: Option<u8>,
+}
+
+impl IndexedImage {
+    // Filled with index 0, which is transparent.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            indices: vec![0; width as usize * height as usize],
+            transparent: Some(0),
+        }
+    }
+
+    pub fn from_indices(width: u32, height: u32, indices: Vec<u8>) -> Result<Self> {
+        if indices.len() != width as usize * height as usize {
+            return Err(Error::IndexCount {
+                len: indices.len(),
+                width,
+                height,
+            });
+        }
+        Ok(Self {
+            width,
+            height,
+            indices,
+            transparent: Some(0),
+        })
+    }
+
+    // Every pixel has to be exactly one of the palette's colours. Fully transparent pixels take
+    // the first transparent entry, which becomes the image's transparent index.
+    pub fn from_pixmap(pixmap: &Pixmap, palette: &Palette) -> Result<Self> {
+        let table = palette.table();
+        let transparent = palette
+            .colours
+            .iter()
+            .position(|colour| colour.alpha() == 0.0)
+            .map(|index| index as u8);
+        let mut lookup = HashMap::new();
+        // the first of any duplicate entries wins
+        for (index, colour) in table.iter().enumerate().take(palette.len()).rev() {
+            lookup.insert(key(*colour), index as u8);
+        }
+
+        let width = pixmap.width();
+        let indices = pixmap
+            .pixels()
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let index = match pixel.alpha() {
+                    0 => transparent,
+                    _ => lookup.get(&key(*pixel)).copied(),
+                };
+                index.ok_or_else(|| {
+                    let colour = pixel.demultiply();
+                    Error::NotInPalette {
+                        colour: [colour.red(), colour.green(), colour.blue(), colour.alpha()],
+                        x: i as u32 % width,
+                        y: i as u32 / width,
+                    }
+                })
+            })
+            .collect::<Result<Vec<u8>>>()?;
+        Ok(Self {
+            width,
+            height: pixmap.height(),
+            indices,
+            transparent,
+        })
+    }
+
+    // Reads any format `corsola::image` can decode and matches it to `palette`.
+    pub fn load(path: impl AsRef<Path>, palette: &Palette) -> Result<Self> {
+        Self::from_pixmap(&image::load(path)?, palette)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Row by row from the top left.
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    pub fn indices_mut(&mut self) -> &mut [u8] {
+        &mut self.indices
+    }
+
+    fn offset(&self, x: i32, y: i32) -> Option<usize> {
+        let inside = (0..self.width as i32).contains(&x) && (0..self.height as i32).contains(&y);
+        inside.then(|| y as usize * self.width as usize + x as usize)
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Option<u8> {
+        Some(self.indices[self.offset(x, y)?])
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, index: u8) {
+        if let Some(offset) = self.offset(x, y) {
+            self.indices[offset] = index;
+        }
+    }
+
+    pub fn fill(&mut self, index: u8) {
+        self.indices.fill(index);
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, index: u8) {
+        let (x0, x1) = clip(x, width, self.width);
+        let (y0, y1) = clip(y, height, self.height);
+        for row in y0..y1 {
+            let start = row * self.width as usize;
+            self.indices[start + x0..start + x1].fill(index);
+        }
+    }
+
+    // Copies `image` in at (x, y), leaving out its transparent pixels.
+    pub fn blit(&mut self, x: i32, y: i32, image: &IndexedImage) {
+        self.blit_ex(x, y, image, &Remap::default());
+    }
+
+    pub fn blit_ex(&mut self, x: i32, y: i32, image: &IndexedImage, remap: &Remap) {
+        let (x0, x1) = clip(x, image.width, self.width);
+        let (y0, y1) = clip(y, image.height, self.height);
+        for row in y0..y1 {
+            let src_row = (row as i32 - y) as usize * image.width as usize;
+            let dst_row = row * self.width as usize;
+            for col in x0..x1 {
+                let index = image.indices[src_row + (col as i32 - x) as usize];
+                if Some(index) != image.transparent {
+                    self.indices[dst_row + col] = remap.get(index);
+                }
+            }
+        }
+    }
+
+    pub fn to_pixmap(&self, palette: &Palette) -> Result<Pixmap> {
+        let mut pixmap = Pixmap::new(self.width, self.height).ok_or(Error::Pixmap {
+            width: self.width,
+            height: self.height,
+        })?;
+        self.resolve(&mut pixmap, 0, 0, 1, palette);
+        Ok(pixmap)
+    }
+
+    pub fn draw(&self, surface: &mut Surface, palette: &Palette) {
+        self.draw_ex(surface, 0, 0, 1, palette);
+    }
+
+    // Resolves the indices through `palette` straight into the surface, each pixel as a `scale`
+    // by `scale` block. Transparent pixels leave the surface as it was.
+    pub fn draw_ex(&self, surface: &mut Surface, x: i32, y: i32, scale: u32, palette: &Palette) {
+        self.resolve(surface.pixmap_mut(), x, y, scale, palette);
+    }
+
+    fn resolve(&self, pixmap: &mut Pixmap, x: i32, y: i32, scale: u32, palette: &Palette) {
+        if self.width == 0 {
+            return;
+        }
+        let table = palette.table();
+        let scale = scale.max(1) as i32;
+        let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+        let pixels = pixmap.pixels_mut();
+        for (src_y, src_row) in self.indices.chunks_exact(self.width as usize).enumerate() {
+            let top = y + src_y as i32 * scale;
+            for dst_y in top.max(0)..(top + scale).min(height) {
+                let dst_row = &mut pixels[(dst_y * width) as usize..][..width as usize];
+                for (src_x, &index) in src_row.iter().enumerate() {
+                    if Some(index) == self.transparent {
+                        continue;
+                    }
+                    let left = x + src_x as i32 * scale;
+                    let span =
+                        left.clamp(0, width) as usize..(left + scale).clamp(0, width) as usize;
+                    let colour = table[index as usize];
+                    for dst in &mut dst_row[span] {
+                        *dst = source_over(colour, *dst);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn key(colour: PremultipliedColorU8) -> [u8; 4] {
+    [colour.red(), colour.green(), colour.blue(), colour.alpha()]
+}
+
+// The part of `start..start + len` inside `0..bound`, as indices.
+fn clip(start: i32, len: u32, bound: u32) -> (usize, usize) {
+    let from = start.clamp(0, bound as i32);
+    let to = (start + len as i32).clamp(from, bound as i32);
+    (from as usize, to as usize)
+}
+
+fn source_over(src: PremultipliedColorU8, dst: PremultipliedColorU8) -> PremultipliedColorU8 {
+    match src.alpha() {
+        255 => src,
+        0 => dst,
+        alpha => {
+            let inv = 255 - alpha as u32;
+            let channel = |s: u8, d: u8| s + ((d as u32 * inv + 127) / 255) as u8;
+            PremultipliedColorU8::from_rgba(
+                channel(src.red(), dst.red()),
+                channel(src.green(), dst.green()),
+                channel(src.blue(), dst.blue()),
+                channel(src.alpha(), dst.alpha()),
+            )
+            .unwrap_or(src)
+        }
+    }
+}
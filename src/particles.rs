@@ -0,0 +1,303 @@
+use crate::{tween::Lerp, Surface};
+use std::f32::consts::TAU;
+use tiny_skia::{
+    BlendMode, Color, FillRule, FilterQuality, Paint, PathBuilder, Pixmap, PixmapPaint, Rect,
+    Transform,
+};
+
+// Keyframes over a particle's normalised lifetime, from 0.0 at spawn to 1.0 at death.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+    pub fn new(keys: impl IntoIterator<Item = (f32, T)>) -> Self {
+        let mut keys: Vec<(f32, T)> = keys.into_iter().collect();
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { keys }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    pub fn linear(from: T, to: T) -> Self {
+        Self {
+            keys: vec![(0.0, from), (1.0, to)],
+        }
+    }
+
+    pub fn sample(&self, t: f32) -> Option<T> {
+        let first = self.keys.first()?;
+        if t <= first.0 {
+            return Some(first.1);
+        }
+        for pair in self.keys.windows(2) {
+            let ((t0, a), (t1, b)) = (pair[0], pair[1]);
+            if t <= t1 {
+                let span = t1 - t0;
+                let f = if span > 0.0 { (t - t0) / span } else { 1.0 };
+                return Some(a.lerp(b, f));
+            }
+        }
+        self.keys.last().map(|key| key.1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterShape {
+    Point,
+    // from the emitter position to the position offset by (dx, dy)
+    Line { dx: f32, dy: f32 },
+    Circle { radius: f32 },
+    Rect { width: f32, height: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParticleShape {
+    Circle,
+    Square,
+    // the colour curve is ignored for sprites, only alpha and size apply
+    Sprite(Pixmap),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleBlend {
+    Normal,
+    Additive,
+}
+
+impl ParticleBlend {
+    fn blend_mode(self) -> BlendMode {
+        match self {
+            ParticleBlend::Normal => BlendMode::SourceOver,
+            ParticleBlend::Additive => BlendMode::Plus,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmitterConfig {
+    pub shape: EmitterShape,
+    // particles per second, 0.0 for burst-only emitters
+    pub rate: f32,
+    pub max_particles: usize,
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    // radians, 0.0 points right and positive angles turn clockwise on screen
+    pub direction: f32,
+    pub spread: f32,
+    pub gravity: (f32, f32),
+    // velocity decays by e^(-drag * dt), so the slowdown doesn't depend on the frame rate
+    pub drag: f32,
+    // diameter in pixels
+    pub size: Curve<f32>,
+    pub colour: Curve<Color>,
+    pub alpha: Curve<f32>,
+    pub particle: ParticleShape,
+    pub blend: ParticleBlend,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self {
+            shape: EmitterShape::Point,
+            rate: 20.0,
+            max_particles: 1000,
+            lifetime: (1.0, 1.0),
+            speed: (50.0, 100.0),
+            direction: 0.0,
+            spread: TAU,
+            gravity: (0.0, 0.0),
+            drag: 0.0,
+            size: Curve::constant(4.0),
+            colour: Curve::constant(Color::WHITE),
+            alpha: Curve::linear(1.0, 0.0),
+            particle: ParticleShape::Circle,
+            blend: ParticleBlend::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    pub fn progress(&self) -> f32 {
+        (self.age / self.lifetime).clamp(0.0, 1.0)
+    }
+}
+
+pub struct ParticleEmitter {
+    pub config: EmitterConfig,
+    pub x: f32,
+    pub y: f32,
+    // inactive emitters stop spawning but keep updating live particles
+    pub active: bool,
+    particles: Vec<Particle>,
+    pending: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(config: EmitterConfig, x: f32, y: f32) -> Self {
+        Self {
+            config,
+            x,
+            y,
+            active: true,
+            particles: Vec::new(),
+            pending: 0.0,
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.active && self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.pending = 0.0;
+    }
+
+    pub fn burst(&mut self, count: usize) {
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.config.max_particles {
+            return;
+        }
+        let cfg = &self.config;
+        let (ox, oy) = match cfg.shape {
+            EmitterShape::Point => (0.0, 0.0),
+            EmitterShape::Line { dx, dy } => {
+                let t = fastrand::f32();
+                (dx * t, dy * t)
+            }
+            EmitterShape::Circle { radius } => {
+                let angle = fastrand::f32() * TAU;
+                let r = radius * fastrand::f32().sqrt();
+                (angle.cos() * r, angle.sin() * r)
+            }
+            EmitterShape::Rect { width, height } => (
+                (fastrand::f32() - 0.5) * width,
+                (fastrand::f32() - 0.5) * height,
+            ),
+        };
+        let angle = cfg.direction + (fastrand::f32() - 0.5) * cfg.spread;
+        let speed = range(cfg.speed);
+
+        self.particles.push(Particle {
+            x: self.x + ox,
+            y: self.y + oy,
+            vx: angle.cos() * speed,
+            vy: angle.sin() * speed,
+            age: 0.0,
+            lifetime: range(cfg.lifetime).max(f32::EPSILON),
+        });
+    }
+
+    // `dt` is in seconds.
+    pub fn update(&mut self, dt: f32) {
+        let (gx, gy) = self.config.gravity;
+        let drag = (-self.config.drag * dt).exp();
+        for p in &mut self.particles {
+            p.age += dt;
+            p.vx = (p.vx + gx * dt) * drag;
+            p.vy = (p.vy + gy * dt) * drag;
+            p.x += p.vx * dt;
+            p.y += p.vy * dt;
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+
+        if self.active && self.config.rate > 0.0 {
+            self.pending += self.config.rate * dt;
+            while self.pending >= 1.0 {
+                self.pending -= 1.0;
+                self.spawn();
+            }
+        }
+    }
+
+    pub fn draw(&self, surface: &mut Surface) {
+        let cfg = &self.config;
+        let blend_mode = cfg.blend.blend_mode();
+        let mut paint = Paint {
+            anti_alias: true,
+            blend_mode,
+            ..Default::default()
+        };
+
+        for p in &self.particles {
+            let t = p.progress();
+            let size = cfg.size.sample(t).unwrap_or(1.0);
+            let alpha = cfg.alpha.sample(t).unwrap_or(1.0).clamp(0.0, 1.0);
+            if size <= 0.0 || alpha <= 0.0 {
+                continue;
+            }
+
+            match &cfg.particle {
+                ParticleShape::Sprite(sprite) => {
+                    let scale = size / sprite.width().max(1) as f32;
+                    let transform = Transform::from_translate(
+                        p.x - sprite.width() as f32 * scale / 2.0,
+                        p.y - sprite.height() as f32 * scale / 2.0,
+                    )
+                    .pre_scale(scale, scale);
+                    surface.blit(
+                        0,
+                        0,
+                        sprite,
+                        &PixmapPaint {
+                            opacity: alpha,
+                            blend_mode,
+                            quality: FilterQuality::Bilinear,
+                        },
+                        transform,
+                        None,
+                    );
+                }
+                shape => {
+                    let path = match shape {
+                        ParticleShape::Circle => PathBuilder::from_circle(p.x, p.y, size / 2.0),
+                        _ => Rect::from_xywh(p.x - size / 2.0, p.y - size / 2.0, size, size)
+                            .map(PathBuilder::from_rect),
+                    };
+                    let Some(path) = path else {
+                        continue;
+                    };
+                    let mut colour = cfg.colour.sample(t).unwrap_or(Color::WHITE);
+                    colour.apply_opacity(alpha);
+                    paint.set_color(colour);
+                    surface.fill_path(
+                        &path,
+                        &paint,
+                        FillRule::Winding,
+                        Transform::identity(),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn range((min, max): (f32, f32)) -> f32 {
+    min + (max - min) * fastrand::f32()
+}
@@ -0,0 +1,302 @@
+// Python bindings for layout scripts and asset preview tools. Everything renders headlessly on the
+// CPU through the same layout code as `Surface::text_ex`. Build with maturin, which picks up the
+// `python` feature from pyproject.toml.
+use crate::{image, vfs::Vfs, Error, FontContext, TextParams};
+use glyphon::{cosmic_text::Align, Attrs, Family};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyIOError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+use std::path::PathBuf;
+use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, PixmapPaint, Rect, Transform};
+
+create_exception!(corsola, CorsolaError, PyException);
+
+impl From<Error> for PyErr {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io { .. } | Error::Archive { .. } => PyIOError::new_err(e.to_string()),
+            e => CorsolaError::new_err(e.to_string()),
+        }
+    }
+}
+
+// Colours are `(r, g, b)` or `(r, g, b, a)` tuples of 0-255.
+#[derive(FromPyObject, Clone, Copy)]
+enum Colour {
+    Rgba(u8, u8, u8, u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Colour {
+    fn rgba(self) -> (u8, u8, u8, u8) {
+        match self {
+            Colour::Rgba(r, g, b, a) => (r, g, b, a),
+            Colour::Rgb(r, g, b) => (r, g, b, 255),
+        }
+    }
+
+    fn skia(self) -> Color {
+        let (r, g, b, a) = self.rgba();
+        Color::from_rgba8(r, g, b, a)
+    }
+
+    fn text(self) -> glyphon::Color {
+        let (r, g, b, a) = self.rgba();
+        glyphon::Color::rgba(r, g, b, a)
+    }
+}
+
+fn align(name: Option<&str>) -> PyResult<Option<Align>> {
+    Ok(match name {
+        None => None,
+        Some("left") => Some(Align::Left),
+        Some("center") | Some("centre") => Some(Align::Center),
+        Some("right") => Some(Align::Right),
+        Some("justified") => Some(Align::Justified),
+        Some(other) => {
+            return Err(PyValueError::new_err(format!(
+                "Unknown alignment '{}', expected left, center, right or justified",
+                other
+            )))
+        }
+    })
+}
+
+fn params<'a>(
+    family: Option<&'a str>,
+    max_width: Option<f32>,
+    align: Option<Align>,
+    line_height: Option<f32>,
+    colour: Option<Colour>,
+) -> TextParams<'a> {
+    let mut attrs = Attrs::new();
+    if let Some(family) = family {
+        attrs = attrs.family(Family::Name(family));
+    }
+    let mut params = TextParams {
+        attrs,
+        align,
+        line_height,
+        dimensions: max_width.map(|w| (w, f32::MAX)),
+        ..Default::default()
+    };
+    if let Some(colour) = colour {
+        params.colour = colour.text();
+    }
+    params
+}
+
+#[pyclass(name = "Fonts")]
+#[derive(Clone)]
+struct PyFonts(FontContext);
+
+#[pymethods]
+impl PyFonts {
+    // Turn off `system_fonts` so previews match on every machine.
+    #[new]
+    #[pyo3(signature = (system_fonts = true))]
+    fn new(system_fonts: bool) -> Self {
+        let fonts = FontContext::new();
+        fonts.set_system_fonts(system_fonts);
+        Self(fonts)
+    }
+
+    fn load_font(&self, path: PathBuf) -> PyResult<usize> {
+        Ok(self.0.load_font_file(path)?)
+    }
+
+    fn load_font_bytes(&self, data: Vec<u8>) -> PyResult<usize> {
+        Ok(self.0.load_font_bytes(data)?)
+    }
+
+    fn load_font_dir(&self, path: PathBuf) -> PyResult<usize> {
+        Ok(self.0.load_font_dir(path)?)
+    }
+
+    fn set_alias(&self, alias: &str, family: &str) {
+        self.0.set_alias(alias, family);
+    }
+
+    fn set_fallback(&self, families: Vec<String>) {
+        self.0.set_fallback(families);
+    }
+
+    fn has_family(&self, name: &str) -> bool {
+        self.0.has_family(name)
+    }
+
+    #[pyo3(signature = (text, size, family = None, max_width = None, line_height = None))]
+    fn measure(
+        &self,
+        text: &str,
+        size: f32,
+        family: Option<&str>,
+        max_width: Option<f32>,
+        line_height: Option<f32>,
+    ) -> PyResult<(f32, f32)> {
+        let params = params(family, max_width, None, line_height, None);
+        Ok(self.0.measure(text, size, &params)?)
+    }
+}
+
+#[pyclass(name = "Image")]
+struct PyImage(Pixmap);
+
+#[pymethods]
+impl PyImage {
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        Ok(Self(image::load(path)?))
+    }
+
+    #[getter]
+    fn width(&self) -> u32 {
+        self.0.width()
+    }
+
+    #[getter]
+    fn height(&self) -> u32 {
+        self.0.height()
+    }
+}
+
+// A headless stand-in for `Surface`.
+#[pyclass]
+struct Canvas {
+    pixmap: Pixmap,
+    fonts: FontContext,
+}
+
+#[pymethods]
+impl Canvas {
+    #[new]
+    #[pyo3(signature = (width, height, fonts = None))]
+    fn new(width: u32, height: u32, fonts: Option<PyRef<PyFonts>>) -> PyResult<Self> {
+        let pixmap = Pixmap::new(width, height).ok_or(Error::Pixmap { width, height })?;
+        Ok(Self {
+            pixmap,
+            fonts: fonts.map_or_else(FontContext::new, |fonts| fonts.0.clone()),
+        })
+    }
+
+    #[getter]
+    fn width(&self) -> u32 {
+        self.pixmap.width()
+    }
+
+    #[getter]
+    fn height(&self) -> u32 {
+        self.pixmap.height()
+    }
+
+    #[getter]
+    fn fonts(&self) -> PyFonts {
+        PyFonts(self.fonts.clone())
+    }
+
+    fn fill(&mut self, colour: Colour) {
+        self.pixmap.fill(colour.skia());
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, colour: Colour) {
+        let Some(rect) = Rect::from_xywh(x, y, width, height) else {
+            return;
+        };
+        let mut paint = Paint::default();
+        paint.set_color(colour.skia());
+        self.pixmap.fill_path(
+            &PathBuilder::from_rect(rect),
+            &paint,
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+
+    #[pyo3(signature = (image, x, y, opacity = 1.0, scale = 1.0))]
+    fn blit(&mut self, image: PyRef<PyImage>, x: f32, y: f32, opacity: f32, scale: f32) {
+        self.pixmap.draw_pixmap(
+            0,
+            0,
+            image.0.as_ref(),
+            &PixmapPaint {
+                opacity,
+                ..Default::default()
+            },
+            Transform::from_scale(scale, scale).post_translate(x, y),
+            None,
+        );
+    }
+
+    // Returns the size of the drawn text.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        text, x, y, size, colour = None, family = None, max_width = None, align = None,
+        line_height = None
+    ))]
+    fn text(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        size: f32,
+        colour: Option<Colour>,
+        family: Option<&str>,
+        max_width: Option<f32>,
+        align: Option<&str>,
+        line_height: Option<f32>,
+    ) -> PyResult<(f32, f32)> {
+        let params = params(family, max_width, self::align(align)?, line_height, colour);
+        Ok(self
+            .fonts
+            .draw_text(&mut self.pixmap, text, x, y, size, &params)?)
+    }
+
+    #[pyo3(signature = (text, size, family = None, max_width = None, line_height = None))]
+    fn measure_text(
+        &self,
+        text: &str,
+        size: f32,
+        family: Option<&str>,
+        max_width: Option<f32>,
+        line_height: Option<f32>,
+    ) -> PyResult<(f32, f32)> {
+        let params = params(family, max_width, None, line_height, None);
+        Ok(self.fonts.measure(text, size, &params)?)
+    }
+
+    fn save_png(&self, path: PathBuf) -> PyResult<()> {
+        self.pixmap.save_png(path).map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn to_png<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let data = self.pixmap.encode_png().map_err(Error::from)?;
+        Ok(PyBytes::new_bound(py, &data))
+    }
+}
+
+// Mounts a directory or zip archive into the virtual filesystem images and fonts are read through.
+#[pyfunction]
+fn mount_dir(point: &str, dir: PathBuf) -> PyResult<()> {
+    Ok(Vfs::global().mount_dir(point, dir)?)
+}
+
+#[pyfunction]
+fn mount_zip(point: &str, path: PathBuf) -> PyResult<()> {
+    Ok(Vfs::global().mount_zip(point, path)?)
+}
+
+#[pymodule]
+fn corsola(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyFonts>()?;
+    m.add_class::<PyImage>()?;
+    m.add_class::<Canvas>()?;
+    m.add_function(wrap_pyfunction!(mount_dir, m)?)?;
+    m.add_function(wrap_pyfunction!(mount_zip, m)?)?;
+    m.add("Error", m.py().get_type_bound::<CorsolaError>())?;
+    Ok(())
+}
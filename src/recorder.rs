@@ -0,0 +1,267 @@
+use crate::{Error, Result, Surface};
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::mpsc::{sync_channel, SyncSender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tiny_skia::Pixmap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordFormat {
+    // numbered `frame_00000.png` files in the directory
+    PngSequence(PathBuf),
+    Gif(PathBuf),
+    Apng(PathBuf),
+}
+
+#[derive(Clone)]
+pub struct Frame {
+    pub pixmap: Pixmap,
+    pub delay: Duration,
+}
+
+// Frames waiting for the writer thread while streaming. Once it falls this far behind, `capture`
+// waits for it rather than holding more full-size frames in memory.
+const STREAM_FRAMES: usize = 8;
+
+// Captures every `every`-th frame through `Surface::screenshot`. Frames are either streamed to a
+// writer thread after `start`, or kept in memory (optionally only the last `max_duration`) until
+// `save` hands them to a writer thread.
+pub struct Recorder {
+    pub every: u32,
+    pub max_duration: Option<Duration>,
+    count: u32,
+    last_capture: Option<Instant>,
+    frames: VecDeque<Frame>,
+    buffered: Duration,
+    stream: Option<(SyncSender<Frame>, JoinHandle<Result<()>>)>,
+}
+
+impl Recorder {
+    pub fn new(every: u32) -> Self {
+        Self {
+            every: every.max(1),
+            max_duration: None,
+            count: 0,
+            last_capture: None,
+            frames: VecDeque::new(),
+            buffered: Duration::ZERO,
+            stream: None,
+        }
+    }
+
+    // Keeps only the most recent `max_duration` of frames, e.g. to save the last 10 seconds.
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    pub fn buffered(&self) -> Duration {
+        self.buffered
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.buffered = Duration::ZERO;
+    }
+
+    // Call after `Surface::update` every frame.
+    pub fn capture(&mut self, surface: &mut Surface) -> Result<()> {
+        self.count += 1;
+        if self.count < self.every {
+            return Ok(());
+        }
+        self.count = 0;
+
+        let now = Instant::now();
+        let delay = self
+            .last_capture
+            .map_or(Duration::from_millis(16), |last| now.duration_since(last));
+        self.last_capture = Some(now);
+        let frame = Frame {
+            pixmap: surface.screenshot()?,
+            delay,
+        };
+
+        if let Some((sender, _)) = &self.stream {
+            sender
+                .send(frame)
+                .map_err(|_| Error::Recording("the writer thread stopped".to_string()))?;
+            return Ok(());
+        }
+
+        self.buffered += frame.delay;
+        self.frames.push_back(frame);
+        if let Some(max) = self.max_duration {
+            while self.buffered > max {
+                match self.frames.pop_front() {
+                    Some(old) => self.buffered -= old.delay,
+                    None => break,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Streams every captured frame to `format` until `stop` is called.
+    pub fn start(&mut self, format: RecordFormat) -> Result<()> {
+        self.stop()?;
+        let (sender, receiver) = sync_channel::<Frame>(STREAM_FRAMES);
+        let handle = thread::spawn(move || write(format, receiver.into_iter()));
+        self.stream = Some((sender, handle));
+        self.last_capture = None;
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        match self.stream.take() {
+            Some((sender, handle)) => {
+                drop(sender);
+                handle
+                    .join()
+                    .map_err(|_| Error::Recording("the writer thread panicked".to_string()))?
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Hands the buffered frames to a background thread to write, leaving the buffer empty. Join
+    // the handle to find out whether writing succeeded.
+    pub fn save(&mut self, format: RecordFormat) -> JoinHandle<Result<()>> {
+        let frames = mem::take(&mut self.frames);
+        self.buffered = Duration::ZERO;
+        thread::spawn(move || write(format, frames.into_iter()))
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+fn write(format: RecordFormat, frames: impl Iterator<Item = Frame>) -> Result<()> {
+    match format {
+        RecordFormat::PngSequence(dir) => {
+            fs::create_dir_all(&dir).map_err(|source| io_error(&dir, source))?;
+            for (i, frame) in frames.enumerate() {
+                frame
+                    .pixmap
+                    .save_png(dir.join(format!("frame_{:05}.png", i)))?;
+            }
+            Ok(())
+        }
+        RecordFormat::Gif(path) => write_gif(path, frames),
+        RecordFormat::Apng(path) => {
+            let spool = path.with_extension("apng.frames");
+            let result = write_apng(&path, &spool, frames);
+            let _ = fs::remove_file(&spool);
+            result
+        }
+    }
+}
+
+fn rgba(pixmap: &Pixmap) -> Vec<u8> {
+    pixmap
+        .pixels()
+        .iter()
+        .flat_map(|px| {
+            let c = px.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect()
+}
+
+fn write_gif(path: PathBuf, mut frames: impl Iterator<Item = Frame>) -> Result<()> {
+    let Some(first) = frames.next() else {
+        return Ok(());
+    };
+    let (width, height) = (first.pixmap.width(), first.pixmap.height());
+    let (Ok(w), Ok(h)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(Error::Recording(format!(
+            "{}x{} frames are too large for a GIF",
+            width, height
+        )));
+    };
+    let file = File::create(&path).map_err(|source| io_error(&path, source))?;
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), w, h, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    for frame in std::iter::once(first).chain(frames) {
+        if (frame.pixmap.width(), frame.pixmap.height()) != (width, height) {
+            return Err(Error::Recording("frame size changed".to_string()));
+        }
+        let mut data = rgba(&frame.pixmap);
+        let mut gif_frame = gif::Frame::from_rgba_speed(w, h, &mut data, 10);
+        // gif delays are in hundredths of a second
+        gif_frame.delay = (frame.delay.as_millis() / 10).clamp(1, u16::MAX as u128) as u16;
+        encoder.write_frame(&gif_frame)?;
+    }
+    Ok(())
+}
+
+// APNG needs the frame count before the first frame, so frames are spooled to disk as they arrive
+// and encoded once the last one is in.
+fn write_apng(path: &Path, spool: &Path, frames: impl Iterator<Item = Frame>) -> Result<()> {
+    let mut size = None;
+    let mut count = 0;
+    let spool_error = |source| io_error(spool, source);
+    let mut out = BufWriter::new(File::create(spool).map_err(spool_error)?);
+    for frame in frames {
+        let frame_size = (frame.pixmap.width(), frame.pixmap.height());
+        if *size.get_or_insert(frame_size) != frame_size {
+            return Err(Error::Recording("frame size changed".to_string()));
+        }
+        let millis = frame.delay.as_millis().clamp(1, u16::MAX as u128) as u16;
+        out.write_all(&millis.to_le_bytes())
+            .and_then(|_| out.write_all(&rgba(&frame.pixmap)))
+            .map_err(spool_error)?;
+        count += 1;
+    }
+    out.flush().map_err(spool_error)?;
+    drop(out);
+    let Some((width, height)) = size else {
+        return Ok(());
+    };
+
+    let file = File::create(path).map_err(|source| io_error(path, source))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(count, 0)?;
+    let mut writer = encoder.write_header()?;
+
+    let mut input = BufReader::new(File::open(spool).map_err(spool_error)?);
+    let mut millis = [0; 2];
+    let mut data = vec![0; width as usize * height as usize * 4];
+    for _ in 0..count {
+        input
+            .read_exact(&mut millis)
+            .and_then(|_| input.read_exact(&mut data))
+            .map_err(spool_error)?;
+        writer.set_frame_delay(u16::from_le_bytes(millis), 1000)?;
+        writer.write_image_data(&data)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+fn io_error(path: &Path, source: io::Error) -> Error {
+    Error::Io {
+        path: path.to_owned(),
+        source,
+    }
+}
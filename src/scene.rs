@@ -0,0 +1,216 @@
+use crate::{
+    transition::{ScreenTransition, TransitionStyle},
+    tween::Easing,
+    Result, Surface,
+};
+use std::time::Instant;
+use winit::{event::WindowEvent, event_loop::ActiveEventLoop};
+
+pub enum SceneChange {
+    None,
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>),
+    Exit,
+}
+
+pub trait Scene {
+    // Called when the scene is pushed onto the stack and when it is removed.
+    fn enter(&mut self) {}
+    fn exit(&mut self) {}
+    // Called when another scene is pushed on top of this one and when it becomes the top again.
+    fn pause(&mut self) {}
+    fn resume(&mut self) {}
+
+    // `dt` is in seconds. Only the top scene is updated.
+    fn update(&mut self, _dt: f32) -> SceneChange {
+        SceneChange::None
+    }
+
+    fn draw(&mut self, surface: &mut Surface) -> Result<()>;
+
+    // Only the top scene receives events.
+    fn event(&mut self, _event: &WindowEvent) -> SceneChange {
+        SceneChange::None
+    }
+
+    // Transparent scenes are overlays, so the scene below them is drawn first.
+    fn is_transparent(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+    last_frame: Option<Instant>,
+    exiting: bool,
+    transition: Option<(TransitionStyle, f32, Easing)>,
+    active: Option<ScreenTransition>,
+    // set when the stack changes, so the next draw starts a transition from the old frame
+    changed: bool,
+}
+
+impl SceneStack {
+    pub fn new(initial: impl Scene + 'static) -> Self {
+        let mut stack = Self::default();
+        stack.push(Box::new(initial));
+        stack
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    // True once a scene returned `SceneChange::Exit` or the last scene was popped.
+    pub fn is_exiting(&self) -> bool {
+        self.exiting || self.scenes.is_empty()
+    }
+
+    pub fn top(&mut self) -> Option<&mut (dyn Scene + 'static)> {
+        self.scenes.last_mut().map(|scene| scene.as_mut())
+    }
+
+    // Plays `style` over `duration` seconds whenever the stack changes.
+    pub fn set_transition(&mut self, style: TransitionStyle, duration: f32, easing: Easing) {
+        self.transition = Some((style, duration, easing));
+    }
+
+    pub fn clear_transition(&mut self) {
+        self.transition = None;
+        self.active = None;
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn push(&mut self, mut scene: Box<dyn Scene>) {
+        if let Some(top) = self.scenes.last_mut() {
+            top.pause();
+        }
+        scene.enter();
+        self.scenes.push(scene);
+        self.changed = true;
+    }
+
+    pub fn pop(&mut self) -> Option<Box<dyn Scene>> {
+        let mut scene = self.scenes.pop()?;
+        scene.exit();
+        if let Some(top) = self.scenes.last_mut() {
+            top.resume();
+        }
+        self.changed = true;
+        Some(scene)
+    }
+
+    pub fn replace(&mut self, mut scene: Box<dyn Scene>) -> Option<Box<dyn Scene>> {
+        let old = self.scenes.pop().map(|mut old| {
+            old.exit();
+            old
+        });
+        scene.enter();
+        self.scenes.push(scene);
+        self.changed = true;
+        old
+    }
+
+    pub fn apply(&mut self, change: SceneChange) {
+        match change {
+            SceneChange::None => {}
+            SceneChange::Push(scene) => self.push(scene),
+            SceneChange::Pop => {
+                self.pop();
+            }
+            SceneChange::Replace(scene) => {
+                self.replace(scene);
+            }
+            SceneChange::Exit => self.exiting = true,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if let Some(active) = &mut self.active {
+            active.update(dt);
+            if active.is_finished() {
+                self.active = None;
+            }
+        }
+        if let Some(top) = self.scenes.last_mut() {
+            let change = top.update(dt);
+            self.apply(change);
+        }
+    }
+
+    pub fn draw(&mut self, surface: &mut Surface) -> Result<()> {
+        if self.changed {
+            self.changed = false;
+            // the screen still shows the last frame of the old scene at this point
+            if let Some((style, duration, easing)) = self.transition {
+                self.active = Some(
+                    ScreenTransition::from_surface(style, duration, surface)?.with_easing(easing),
+                );
+            }
+        }
+
+        let first = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_transparent())
+            .unwrap_or(0);
+        for scene in &mut self.scenes[first..] {
+            scene.draw(surface)?;
+        }
+        if let Some(active) = &mut self.active {
+            active.draw(surface);
+        }
+        Ok(())
+    }
+
+    pub fn event(&mut self, event: &WindowEvent) {
+        if let Some(top) = self.scenes.last_mut() {
+            let change = top.event(event);
+            self.apply(change);
+        }
+    }
+
+    // Drives the stack from `ApplicationHandler::window_event`: closes on `CloseRequested`,
+    // updates and draws on `RedrawRequested` and forwards everything else to the top scene.
+    pub fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        surface: &mut Surface,
+        event: WindowEvent,
+    ) -> Result<()> {
+        match event {
+            WindowEvent::CloseRequested => {
+                self.exiting = true;
+            }
+            WindowEvent::RedrawRequested => {
+                let now = Instant::now();
+                let dt = self
+                    .last_frame
+                    .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+                self.last_frame = Some(now);
+
+                self.update(dt);
+                self.draw(surface)?;
+                surface.update()?;
+                surface.request_redraw();
+            }
+            event => self.event(&event),
+        }
+
+        if self.is_exiting() {
+            while let Some(mut scene) = self.scenes.pop() {
+                scene.exit();
+            }
+            event_loop.exit();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,492 @@
+// Rhai scripts for game logic. A script is a set of hooks, any of which may be left out:
+//
+//     fn init() { this.x = 0.0; }
+//     fn update(dt) {
+//         this.x += axis("move") * 200.0 * dt;
+//         if just_pressed("jump") { play("jump"); }
+//     }
+//     fn draw() {
+//         fill(rgb(20, 20, 60));
+//         blit("card", this.x, 100.0);
+//         text("Hello", 20.0, 20.0, 32.0, rgb(255, 255, 255));
+//     }
+//     fn reloaded() { print("reloaded"); }
+//
+// Every hook runs with `this` bound to the same object map, so state survives hot reloads. Numbers
+// are f32, so coordinates need a decimal point: `10.0` rather than `10`. Drawing and sounds are
+// queued while the script runs and applied afterwards by `draw` and `update`.
+use crate::{
+    audio::{Audio, SoundId},
+    input::ActionMap,
+    vfs::Vfs,
+    Error, Result, Surface,
+};
+use kira::manager::backend::Backend;
+use rhai::{CallFnOptions, Dynamic, Engine, FuncArgs, Map, Scope, AST, FLOAT, INT};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+use tiny_skia::{
+    Color, FillRule, Paint, PathBuilder, Pixmap, PixmapPaint, Rect, Stroke, Transform,
+};
+
+enum Command {
+    Fill(Color),
+    FillRect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        colour: Color,
+    },
+    FillCircle {
+        x: f32,
+        y: f32,
+        radius: f32,
+        colour: Color,
+    },
+    Line {
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+        colour: Color,
+    },
+    Blit {
+        image: String,
+        x: f32,
+        y: f32,
+        opacity: f32,
+        scale: f32,
+    },
+    Text {
+        text: String,
+        x: f32,
+        y: f32,
+        size: f32,
+        colour: Color,
+    },
+}
+
+// What the script can see and do during one hook call.
+#[derive(Default)]
+struct Frame {
+    commands: Vec<Command>,
+    sounds: Vec<String>,
+    down: HashSet<String>,
+    pressed: HashSet<String>,
+    released: HashSet<String>,
+    axes: HashMap<String, f32>,
+    size: (u32, u32),
+}
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    state: Dynamic,
+    frame: Rc<RefCell<Frame>>,
+    images: HashMap<String, Pixmap>,
+    sounds: HashMap<String, SoundId>,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl Script {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let frame = Rc::new(RefCell::new(Frame::default()));
+        let mut engine = Engine::new();
+        register(&mut engine, &frame);
+
+        let path = path.as_ref().to_owned();
+        let modified = modified(&path);
+        let ast = compile(&engine, &path)?;
+        let mut script = Self {
+            engine,
+            ast,
+            state: Dynamic::from_map(Map::new()),
+            frame,
+            images: HashMap::new(),
+            sounds: HashMap::new(),
+            path,
+            modified,
+        };
+        script.call_hook("init", ())?;
+        Ok(script)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The object map bound to `this`.
+    pub fn state(&self) -> &Dynamic {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut Dynamic {
+        &mut self.state
+    }
+
+    // For registering game specific functions and types on top of the corsola API.
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    // Images and sounds are looked up by these names from `blit` and `play`.
+    pub fn add_image(&mut self, name: &str, pixmap: Pixmap) {
+        self.images.insert(name.to_owned(), pixmap);
+    }
+
+    pub fn add_sound(&mut self, name: &str, sound: SoundId) {
+        self.sounds.insert(name.to_owned(), sound);
+    }
+
+    // Recompiles the script and calls its `reloaded` hook. If it doesn't compile the previous
+    // version keeps running, so a typo doesn't take the game down mid-session, and
+    // `reload_if_changed` tries again until it does.
+    pub fn reload(&mut self) -> Result<()> {
+        let modified = modified(&self.path);
+        self.ast = compile(&self.engine, &self.path)?;
+        self.modified = modified;
+        self.call_hook("reloaded", ())?;
+        Ok(())
+    }
+
+    // Cheap enough to call every frame. Returns whether the script was reloaded.
+    pub fn reload_if_changed(&mut self) -> Result<bool> {
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    pub fn has_fn(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+
+    // Calls any script function with `this` bound, e.g. per-card effects like `on_play(card)`.
+    pub fn call(&mut self, name: &str, args: impl FuncArgs) -> Result<Dynamic> {
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, args)
+            .map_err(|e| Error::Script {
+                path: self.path.clone(),
+                message: e.to_string(),
+            })
+    }
+
+    fn call_hook(&mut self, name: &str, args: impl FuncArgs) -> Result<()> {
+        if self.has_fn(name) {
+            self.call(name, args)?;
+        }
+        Ok(())
+    }
+
+    pub fn update<B: Backend>(
+        &mut self,
+        dt: f32,
+        input: &ActionMap,
+        audio: &mut Audio<B>,
+    ) -> Result<()> {
+        {
+            let mut frame = self.frame.borrow_mut();
+            let actions = input.bindings.actions.keys();
+            frame.down = actions
+                .clone()
+                .filter(|a| input.is_down(a))
+                .cloned()
+                .collect();
+            frame.pressed = actions
+                .clone()
+                .filter(|a| input.just_pressed(a))
+                .cloned()
+                .collect();
+            frame.released = actions
+                .filter(|a| input.just_released(a))
+                .cloned()
+                .collect();
+            frame.axes = input
+                .bindings
+                .axes
+                .keys()
+                .map(|a| (a.clone(), input.axis(a)))
+                .collect();
+        }
+        self.call_hook("update", (dt,))?;
+
+        // names are resolved up front so a typo doesn't leave half of the frame's sounds played
+        let sounds = std::mem::take(&mut self.frame.borrow_mut().sounds)
+            .iter()
+            .map(|name| {
+                self.sounds
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| Error::NotFound(format!("Sound '{}'", name)))
+            })
+            .collect::<Result<Vec<SoundId>>>()?;
+        for sound in sounds {
+            audio.play(sound)?;
+        }
+        Ok(())
+    }
+
+    pub fn draw(&mut self, surface: &mut Surface) -> Result<()> {
+        {
+            let mut frame = self.frame.borrow_mut();
+            frame.size = surface.size();
+            frame.commands.clear();
+        }
+        self.call_hook("draw", ())?;
+
+        let commands = std::mem::take(&mut self.frame.borrow_mut().commands);
+        for command in commands {
+            match command {
+                Command::Fill(colour) => surface.fill(colour),
+                Command::FillRect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    colour,
+                } => {
+                    if let Some(rect) = Rect::from_xywh(x, y, width, height) {
+                        surface.fill_path(
+                            &PathBuilder::from_rect(rect),
+                            &paint(colour),
+                            FillRule::Winding,
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                }
+                Command::FillCircle {
+                    x,
+                    y,
+                    radius,
+                    colour,
+                } => {
+                    if let Some(path) = PathBuilder::from_circle(x, y, radius) {
+                        surface.fill_path(
+                            &path,
+                            &paint(colour),
+                            FillRule::Winding,
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                }
+                Command::Line {
+                    from,
+                    to,
+                    width,
+                    colour,
+                } => {
+                    let mut pb = PathBuilder::new();
+                    pb.move_to(from.0, from.1);
+                    pb.line_to(to.0, to.1);
+                    if let Some(path) = pb.finish() {
+                        surface.pixmap_mut().stroke_path(
+                            &path,
+                            &paint(colour),
+                            &Stroke {
+                                width,
+                                ..Default::default()
+                            },
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                }
+                Command::Blit {
+                    image,
+                    x,
+                    y,
+                    opacity,
+                    scale,
+                } => {
+                    let pixmap = self
+                        .images
+                        .get(&image)
+                        .ok_or_else(|| Error::NotFound(format!("Image '{}'", image)))?;
+                    surface.blit(
+                        0,
+                        0,
+                        pixmap,
+                        &PixmapPaint {
+                            opacity,
+                            ..Default::default()
+                        },
+                        Transform::from_scale(scale, scale).post_translate(x, y),
+                        None,
+                    );
+                }
+                Command::Text {
+                    text,
+                    x,
+                    y,
+                    size,
+                    colour,
+                } => {
+                    let c = colour.to_color_u8();
+                    surface.text(
+                        &text,
+                        x,
+                        y,
+                        size,
+                        glyphon::Color::rgba(c.red(), c.green(), c.blue(), c.alpha()),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn compile(engine: &Engine, path: &Path) -> Result<AST> {
+    let src = Vfs::global().read_to_string(path)?;
+    engine.compile(src).map_err(|e| Error::Script {
+        path: path.to_owned(),
+        message: e.to_string(),
+    })
+}
+
+// Scripts in archives or embedded in the binary never change.
+fn modified(path: &Path) -> Option<SystemTime> {
+    let real = Vfs::global().real_path(path)?;
+    fs::metadata(real).and_then(|m| m.modified()).ok()
+}
+
+fn paint(colour: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(colour);
+    paint
+}
+
+fn channel(value: INT) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+fn register(engine: &mut Engine, frame: &Rc<RefCell<Frame>>) {
+    engine.register_type_with_name::<Color>("Color");
+    engine.register_fn("rgb", |r: INT, g: INT, b: INT| {
+        Color::from_rgba8(channel(r), channel(g), channel(b), 255)
+    });
+    engine.register_fn("rgba", |r: INT, g: INT, b: INT, a: INT| {
+        Color::from_rgba8(channel(r), channel(g), channel(b), channel(a))
+    });
+
+    // drawing
+    let f = frame.clone();
+    engine.register_fn("fill", move |colour: Color| {
+        f.borrow_mut().commands.push(Command::Fill(colour));
+    });
+    let f = frame.clone();
+    engine.register_fn(
+        "fill_rect",
+        move |x: FLOAT, y: FLOAT, width: FLOAT, height: FLOAT, colour: Color| {
+            f.borrow_mut().commands.push(Command::FillRect {
+                x,
+                y,
+                width,
+                height,
+                colour,
+            });
+        },
+    );
+    let f = frame.clone();
+    engine.register_fn(
+        "fill_circle",
+        move |x: FLOAT, y: FLOAT, radius: FLOAT, colour: Color| {
+            f.borrow_mut().commands.push(Command::FillCircle {
+                x,
+                y,
+                radius,
+                colour,
+            });
+        },
+    );
+    let f = frame.clone();
+    engine.register_fn(
+        "line",
+        move |x0: FLOAT, y0: FLOAT, x1: FLOAT, y1: FLOAT, width: FLOAT, colour: Color| {
+            f.borrow_mut().commands.push(Command::Line {
+                from: (x0, y0),
+                to: (x1, y1),
+                width,
+                colour,
+            });
+        },
+    );
+    let f = frame.clone();
+    engine.register_fn("blit", move |image: &str, x: FLOAT, y: FLOAT| {
+        f.borrow_mut().commands.push(Command::Blit {
+            image: image.to_owned(),
+            x,
+            y,
+            opacity: 1.0,
+            scale: 1.0,
+        });
+    });
+    let f = frame.clone();
+    engine.register_fn(
+        "blit",
+        move |image: &str, x: FLOAT, y: FLOAT, opacity: FLOAT, scale: FLOAT| {
+            f.borrow_mut().commands.push(Command::Blit {
+                image: image.to_owned(),
+                x,
+                y,
+                opacity,
+                scale,
+            });
+        },
+    );
+    let f = frame.clone();
+    engine.register_fn(
+        "text",
+        move |text: &str, x: FLOAT, y: FLOAT, size: FLOAT, colour: Color| {
+            f.borrow_mut().commands.push(Command::Text {
+                text: text.to_owned(),
+                x,
+                y,
+                size,
+                colour,
+            });
+        },
+    );
+    let f = frame.clone();
+    engine.register_fn("width", move || f.borrow().size.0 as FLOAT);
+    let f = frame.clone();
+    engine.register_fn("height", move || f.borrow().size.1 as FLOAT);
+
+    // input
+    let f = frame.clone();
+    engine.register_fn("is_down", move |action: &str| {
+        f.borrow().down.contains(action)
+    });
+    let f = frame.clone();
+    engine.register_fn("just_pressed", move |action: &str| {
+        f.borrow().pressed.contains(action)
+    });
+    let f = frame.clone();
+    engine.register_fn("just_released", move |action: &str| {
+        f.borrow().released.contains(action)
+    });
+    let f = frame.clone();
+    engine.register_fn("axis", move |axis: &str| {
+        f.borrow().axes.get(axis).copied().unwrap_or(0.0)
+    });
+
+    // audio
+    let f = frame.clone();
+    engine.register_fn("play", move |sound: &str| {
+        f.borrow_mut().sounds.push(sound.to_owned());
+    });
+}
@@ -0,0 +1,277 @@
+use crate::{
+    image::{self, from_rgba, ImageFormat},
+    tween::{Animation, Repeat},
+    vfs::Vfs,
+    Error, Result, Surface,
+};
+use ::image::{
+    codecs::{gif::GifDecoder, png::PngDecoder},
+    AnimationDecoder, Frames,
+};
+use std::{io::Cursor, path::Path, rc::Rc};
+use tiny_skia::{Pixmap, PixmapPaint, Transform};
+
+// Browsers play GIF and APNG frames with a delay of 10ms or less at 100ms, and files are made to
+// look right there.
+const MIN_DECODED_DELAY: f32 = 0.011;
+const DEFAULT_DELAY: f32 = 0.1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub pixmap: Pixmap,
+    // in seconds
+    pub delay: f32,
+}
+
+// The frames of an animated GIF or APNG. Each frame is the whole canvas with the disposal and
+// blending of the frames before it already applied, so any one can be drawn on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatedImage {
+    frames: Vec<Frame>,
+}
+
+impl AnimatedImage {
+    // Returns `None` if there are no frames. Delays are clamped to at least a millisecond.
+    pub fn new(frames: Vec<Frame>) -> Option<Self> {
+        if frames.is_empty() {
+            return None;
+        }
+        let frames = frames
+            .into_iter()
+            .map(|frame| Frame {
+                delay: frame.delay.max(0.001),
+                ..frame
+            })
+            .collect();
+        Some(Self { frames })
+    }
+
+    // Reads an animation through the `Vfs`. Still images of any format `corsola::image` decodes
+    // load as a single frame.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = Vfs::global().read(path)?;
+        let name = path.display().to_string();
+        let format = ImageFormat::guess(&data)
+            .or_else(|| ImageFormat::from_path(path))
+            .ok_or_else(|| Error::UnsupportedImage(name.clone()))?;
+        Self::decode_named(&data, format, name)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let name = "image data".to_string();
+        let format =
+            ImageFormat::guess(data).ok_or_else(|| Error::UnsupportedImage(name.clone()))?;
+        Self::decode_named(data, format, name)
+    }
+
+    fn decode_named(data: &[u8], format: ImageFormat, name: String) -> Result<Self> {
+        let error = |source| Error::Image {
+            name: name.clone(),
+            source,
+        };
+        let frames: Frames = match format {
+            ImageFormat::Gif => GifDecoder::new(Cursor::new(data))
+                .map_err(error)?
+                .into_frames(),
+            ImageFormat::Png => {
+                let decoder = PngDecoder::new(Cursor::new(data)).map_err(error)?;
+                if !decoder.is_apng().map_err(error)? {
+                    return Ok(Self::still(image::decode_named(
+                        data,
+                        format,
+                        name.clone(),
+                    )?));
+                }
+                decoder.apng().map_err(error)?.into_frames()
+            }
+            format => {
+                return Ok(Self::still(image::decode_named(
+                    data,
+                    format,
+                    name.clone(),
+                )?))
+            }
+        };
+
+        let frames = frames
+            .map(|frame| {
+                let frame = frame.map_err(error)?;
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                let delay = match denom {
+                    0 => DEFAULT_DELAY,
+                    denom => numer as f32 / denom as f32 / 1000.0,
+                };
+                let buffer = frame.into_buffer();
+                Ok(Frame {
+                    pixmap: from_rgba(buffer.width(), buffer.height(), buffer.as_raw())?,
+                    delay: match delay < MIN_DECODED_DELAY {
+                        true => DEFAULT_DELAY,
+                        false => delay,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(frames).ok_or(Error::UnsupportedImage(name))
+    }
+
+    fn still(pixmap: Pixmap) -> Self {
+        Self {
+            frames: vec![Frame {
+                pixmap,
+                delay: DEFAULT_DELAY,
+            }],
+        }
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn width(&self) -> u32 {
+        self.frames[0].pixmap.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.frames[0].pixmap.height()
+    }
+
+    // One play through, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.delay).sum()
+    }
+}
+
+// Plays an `AnimatedImage`, which can be shared between any number of sprites. Loops forever
+// unless given another `Repeat`, and can be put in tween sequences to play once before
+// something else happens.
+#[derive(Debug, Clone)]
+pub struct AnimatedSprite {
+    image: Rc<AnimatedImage>,
+    pub repeat: Repeat,
+    // 2.0 plays twice as fast
+    pub speed: f32,
+    pub paused: bool,
+    frame: usize,
+    // time spent on the current frame
+    elapsed: f32,
+    cycles: u32,
+    finished: bool,
+}
+
+impl AnimatedSprite {
+    pub fn new(image: Rc<AnimatedImage>) -> Self {
+        Self {
+            image,
+            repeat: Repeat::Forever,
+            speed: 1.0,
+            paused: false,
+            frame: 0,
+            elapsed: 0.0,
+            cycles: 0,
+            finished: false,
+        }
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn image(&self) -> &Rc<AnimatedImage> {
+        &self.image
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn current(&self) -> &Pixmap {
+        &self.image.frames[self.frame].pixmap
+    }
+
+    // Jumps to the start of `frame`, wrapping past the last one.
+    pub fn seek(&mut self, frame: usize) {
+        self.frame = frame % self.image.frames.len();
+        self.elapsed = 0.0;
+        self.finished = false;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.advance(dt);
+    }
+
+    pub fn draw(&self, surface: &mut Surface, x: i32, y: i32) {
+        self.draw_ex(
+            surface,
+            x,
+            y,
+            &PixmapPaint::default(),
+            Transform::identity(),
+        );
+    }
+
+    pub fn draw_ex(
+        &self,
+        surface: &mut Surface,
+        x: i32,
+        y: i32,
+        paint: &PixmapPaint,
+        transform: Transform,
+    ) {
+        surface.blit(x, y, self.current(), paint, transform, None);
+    }
+
+    fn is_last_cycle(&self) -> bool {
+        match self.repeat {
+            Repeat::Never => true,
+            Repeat::Times(n) => self.cycles >= n,
+            Repeat::Forever => false,
+        }
+    }
+}
+
+impl Animation for AnimatedSprite {
+    fn advance(&mut self, dt: f32) -> f32 {
+        if self.finished {
+            return dt;
+        }
+        if self.paused {
+            return 0.0;
+        }
+        let frames = &self.image.frames;
+        self.elapsed += dt * self.speed;
+        while self.elapsed >= frames[self.frame].delay {
+            self.elapsed -= frames[self.frame].delay;
+            if self.frame + 1 < frames.len() {
+                self.frame += 1;
+            } else if self.is_last_cycle() {
+                // stays on the last frame
+                self.finished = true;
+                let left = self.elapsed / self.speed.max(f32::EPSILON);
+                self.elapsed = 0.0;
+                return left;
+            } else {
+                self.cycles += 1;
+                self.frame = 0;
+            }
+        }
+        0.0
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn reset(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.cycles = 0;
+        self.finished = false;
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{image, Error, Result};
+use std::path::{Path, PathBuf};
+use tiny_skia::Pixmap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(usize);
+
+// Pixmaps behind stable ids, so a texture can be swapped for a new version of its file without
+// anything holding the id noticing.
+#[derive(Default)]
+pub struct Textures {
+    pixmaps: Vec<(Pixmap, Option<PathBuf>)>,
+}
+
+impl Textures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<TextureId> {
+        let path = path.as_ref();
+        let pixmap = image::load(path)?;
+        self.pixmaps.push((pixmap, Some(path.to_owned())));
+        Ok(TextureId(self.pixmaps.len() - 1))
+    }
+
+    pub fn add(&mut self, pixmap: Pixmap) -> TextureId {
+        self.pixmaps.push((pixmap, None));
+        TextureId(self.pixmaps.len() - 1)
+    }
+
+    pub fn get(&self, texture: TextureId) -> Option<&Pixmap> {
+        self.pixmaps.get(texture.0).map(|(pixmap, _)| pixmap)
+    }
+
+    pub fn get_mut(&mut self, texture: TextureId) -> Option<&mut Pixmap> {
+        self.pixmaps.get_mut(texture.0).map(|(pixmap, _)| pixmap)
+    }
+
+    // `None` for textures added from memory.
+    pub fn path(&self, texture: TextureId) -> Option<&Path> {
+        self.pixmaps.get(texture.0)?.1.as_deref()
+    }
+
+    pub fn replace(&mut self, texture: TextureId, pixmap: Pixmap) -> Result<()> {
+        let entry = self
+            .pixmaps
+            .get_mut(texture.0)
+            .ok_or_else(|| Error::NotFound(format!("Texture {:?}", texture)))?;
+        entry.0 = pixmap;
+        Ok(())
+    }
+
+    // Reads the texture's file again. On error the old pixmap is kept.
+    pub fn reload(&mut self, texture: TextureId) -> Result<()> {
+        let path = self
+            .path(texture)
+            .ok_or_else(|| Error::NoPath(format!("Texture {:?}", texture)))?;
+        let pixmap = image::load(path)?;
+        self.replace(texture, pixmap)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TextureId, &Pixmap)> {
+        self.pixmaps
+            .iter()
+            .enumerate()
+            .map(|(i, (pixmap, _))| (TextureId(i), pixmap))
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{tween::Easing, Result, Surface};
+use tiny_skia::{Color, FillRule, Mask, Paint, PathBuilder, Pixmap, PixmapPaint, Rect, Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionStyle {
+    // fades the outgoing scene to the colour over the first half, then the colour to the incoming
+    Fade(Color),
+    Crossfade,
+    // the edge between the scenes travels in the given direction
+    Wipe(WipeDirection),
+    // the incoming scene opens up from a circle in the middle of the screen
+    Iris,
+    Dissolve { block_size: u32 },
+}
+
+// Composites a snapshot of the outgoing frame over the incoming scene, which keeps being drawn
+// and updated as normal underneath, so nothing blocks while the transition plays.
+pub struct ScreenTransition {
+    pub style: TransitionStyle,
+    pub duration: f32,
+    pub easing: Easing,
+    elapsed: f32,
+    from: Pixmap,
+    mask: Option<Mask>,
+    // random per-block dissolve order, regenerated if the surface size changes
+    thresholds: Vec<u8>,
+    threshold_size: (u32, u32),
+}
+
+impl ScreenTransition {
+    // `duration` is in seconds.
+    pub fn new(style: TransitionStyle, duration: f32, from: Pixmap) -> Self {
+        Self {
+            style,
+            duration,
+            easing: Easing::Linear,
+            elapsed: 0.0,
+            from,
+            mask: None,
+            thresholds: Vec::new(),
+            threshold_size: (0, 0),
+        }
+    }
+
+    // Snapshots what is currently on screen as the outgoing frame.
+    pub fn from_surface(
+        style: TransitionStyle,
+        duration: f32,
+        surface: &mut Surface,
+    ) -> Result<Self> {
+        Ok(Self::new(style, duration, surface.screenshot()?))
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            self.easing.apply(self.elapsed / self.duration)
+        } else {
+            1.0
+        }
+    }
+
+    // Call after the incoming scene has been drawn for this frame.
+    pub fn draw(&mut self, surface: &mut Surface) {
+        let t = self.progress();
+        if t >= 1.0 {
+            return;
+        }
+        let (width, height) = surface.size();
+
+        match self.style {
+            TransitionStyle::Fade(colour) => {
+                if t < 0.5 {
+                    self.draw_from(surface, 1.0, false);
+                }
+                let mut colour = colour;
+                colour.apply_opacity(1.0 - (t * 2.0 - 1.0).abs());
+                fill(surface, colour, width, height);
+            }
+            TransitionStyle::Crossfade => self.draw_from(surface, 1.0 - t, false),
+            TransitionStyle::Wipe(direction) => {
+                let (w, h) = (width as f32, height as f32);
+                let rect = match direction {
+                    WipeDirection::Left => Rect::from_xywh(0.0, 0.0, w * (1.0 - t), h),
+                    WipeDirection::Right => Rect::from_xywh(w * t, 0.0, w * (1.0 - t), h),
+                    WipeDirection::Up => Rect::from_xywh(0.0, 0.0, w, h * (1.0 - t)),
+                    WipeDirection::Down => Rect::from_xywh(0.0, h * t, w, h * (1.0 - t)),
+                };
+                let Some(mask) = cleared_mask(&mut self.mask, width, height) else {
+                    return;
+                };
+                if let Some(rect) = rect {
+                    mask.fill_path(
+                        &PathBuilder::from_rect(rect),
+                        FillRule::Winding,
+                        false,
+                        Transform::identity(),
+                    );
+                }
+                self.draw_from(surface, 1.0, true);
+            }
+            TransitionStyle::Iris => {
+                let radius = t * (width as f32).hypot(height as f32) / 2.0;
+                let circle =
+                    PathBuilder::from_circle(width as f32 / 2.0, height as f32 / 2.0, radius);
+                let Some(mask) = cleared_mask(&mut self.mask, width, height) else {
+                    return;
+                };
+                if let Some(circle) = circle {
+                    mask.fill_path(&circle, FillRule::Winding, true, Transform::identity());
+                }
+                mask.invert();
+                self.draw_from(surface, 1.0, true);
+            }
+            TransitionStyle::Dissolve { block_size } => {
+                let block_size = block_size.max(1);
+                let blocks_x = width.div_ceil(block_size);
+                if self.threshold_size != (width, height) {
+                    let blocks = blocks_x * height.div_ceil(block_size);
+                    self.thresholds = (0..blocks).map(|_| fastrand::u8(..)).collect();
+                    self.threshold_size = (width, height);
+                }
+                let cutoff = t * 256.0;
+                let Some(mask) = cleared_mask(&mut self.mask, width, height) else {
+                    return;
+                };
+                for (i, px) in mask.data_mut().iter_mut().enumerate() {
+                    let (x, y) = (i as u32 % width, i as u32 / width);
+                    let block = (y / block_size) * blocks_x + x / block_size;
+                    *px = if self.thresholds[block as usize] as f32 >= cutoff {
+                        255
+                    } else {
+                        0
+                    };
+                }
+                self.draw_from(surface, 1.0, true);
+            }
+        }
+    }
+
+    fn draw_from(&self, surface: &mut Surface, opacity: f32, masked: bool) {
+        let mask = if masked { self.mask.as_ref() } else { None };
+        surface.blit(
+            0,
+            0,
+            &self.from,
+            &PixmapPaint {
+                opacity,
+                ..Default::default()
+            },
+            Transform::identity(),
+            mask,
+        );
+    }
+}
+
+fn fill(surface: &mut Surface, colour: Color, width: u32, height: u32) {
+    let Some(rect) = Rect::from_xywh(0.0, 0.0, width as f32, height as f32) else {
+        return;
+    };
+    let mut paint = Paint::default();
+    paint.set_color(colour);
+    surface.fill_path(
+        &PathBuilder::from_rect(rect),
+        &paint,
+        FillRule::Winding,
+        Transform::identity(),
+        None,
+    );
+}
+
+// Returns the cached mask cleared and resized to match the surface.
+fn cleared_mask(slot: &mut Option<Mask>, width: u32, height: u32) -> Option<&mut Mask> {
+    if slot
+        .as_ref()
+        .is_none_or(|m| m.width() != width || m.height() != height)
+    {
+        *slot = Mask::new(width, height);
+    }
+    let mask = slot.as_mut()?;
+    mask.clear();
+    Some(mask)
+}
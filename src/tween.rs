@@ -0,0 +1,516 @@
+use std::f32::consts::PI;
+
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for (f32, f32) {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        (self.0.lerp(other.0, t), self.1.lerp(other.1, t))
+    }
+}
+
+impl Lerp for tiny_skia::Color {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        // overshooting easings like back and elastic can leave 0.0..=1.0, so clamp
+        tiny_skia::Color::from_rgba(
+            self.red().lerp(other.red(), t).clamp(0.0, 1.0),
+            self.green().lerp(other.green(), t).clamp(0.0, 1.0),
+            self.blue().lerp(other.blue(), t).clamp(0.0, 1.0),
+            self.alpha().lerp(other.alpha(), t).clamp(0.0, 1.0),
+        )
+        .unwrap_or(other)
+    }
+}
+
+impl Lerp for glyphon::Color {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        let channel = |a: u8, b: u8| (a as f32).lerp(b as f32, t).round().clamp(0.0, 255.0) as u8;
+        glyphon::Color::rgba(
+            channel(self.r(), other.r()),
+            channel(self.g(), other.g()),
+            channel(self.b(), other.b()),
+            channel(self.a(), other.a()),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    QuintIn,
+    QuintOut,
+    QuintInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    // Maps linear progress in 0.0..=1.0 to eased progress. Back and elastic overshoot that range.
+    pub fn apply(self, t: f32) -> f32 {
+        const C1: f32 = 1.70158;
+        const C2: f32 = C1 * 1.525;
+        const C3: f32 = C1 + 1.0;
+        const C4: f32 = 2.0 * PI / 3.0;
+        const C5: f32 = 2.0 * PI / 4.5;
+
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Easing::QuadInOut => in_out(t, |t| t * t),
+            Easing::CubicIn => t.powi(3),
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => in_out(t, |t| t.powi(3)),
+            Easing::QuartIn => t.powi(4),
+            Easing::QuartOut => 1.0 - (1.0 - t).powi(4),
+            Easing::QuartInOut => in_out(t, |t| t.powi(4)),
+            Easing::QuintIn => t.powi(5),
+            Easing::QuintOut => 1.0 - (1.0 - t).powi(5),
+            Easing::QuintInOut => in_out(t, |t| t.powi(5)),
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::ExpoIn => expo_in(t),
+            Easing::ExpoOut => 1.0 - expo_in(1.0 - t),
+            Easing::ExpoInOut => in_out(t, expo_in),
+            Easing::CircIn => 1.0 - (1.0 - t * t).sqrt(),
+            Easing::CircOut => (1.0 - (t - 1.0).powi(2)).sqrt(),
+            Easing::CircInOut => in_out(t, |t| 1.0 - (1.0 - t * t).sqrt()),
+            Easing::BackIn => C3 * t.powi(3) - C1 * t * t,
+            Easing::BackOut => 1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2),
+            Easing::BackInOut => in_out(t, |t| (C2 + 1.0) * t.powi(3) - C2 * t * t),
+            Easing::ElasticIn => match t {
+                0.0 | 1.0 => t,
+                _ => -(2f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * C4).sin(),
+            },
+            Easing::ElasticOut => match t {
+                0.0 | 1.0 => t,
+                _ => 2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * C4).sin() + 1.0,
+            },
+            Easing::ElasticInOut => match t {
+                0.0 | 1.0 => t,
+                _ if t < 0.5 => {
+                    -(2f32.powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * C5).sin()) / 2.0
+                }
+                _ => 2f32.powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * C5).sin() / 2.0 + 1.0,
+            },
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => in_out(t, |t| 1.0 - bounce_out(1.0 - t)),
+        }
+    }
+}
+
+// Builds the in-out variant of an ease-in curve by mirroring it around the midpoint.
+fn in_out(t: f32, ease_in: impl Fn(f32) -> f32) -> f32 {
+    if t < 0.5 {
+        ease_in(t * 2.0) / 2.0
+    } else {
+        1.0 - ease_in((1.0 - t) * 2.0) / 2.0
+    }
+}
+
+fn expo_in(t: f32) -> f32 {
+    if t == 0.0 {
+        0.0
+    } else {
+        2f32.powf(10.0 * t - 10.0)
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N1: f32 = 7.5625;
+    const D1: f32 = 2.75;
+    if t < 1.0 / D1 {
+        N1 * t * t
+    } else if t < 2.0 / D1 {
+        let t = t - 1.5 / D1;
+        N1 * t * t + 0.75
+    } else if t < 2.5 / D1 {
+        let t = t - 2.25 / D1;
+        N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D1;
+        N1 * t * t + 0.984375
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Repeat {
+    #[default]
+    Never,
+    Times(u32),
+    Forever,
+}
+
+// Anything that can be stepped along in time and grouped into sequences and parallel groups.
+pub trait Animation {
+    // Advances by `dt` seconds and returns how much of `dt` was left over after finishing.
+    fn advance(&mut self, dt: f32) -> f32;
+    fn is_finished(&self) -> bool;
+    fn reset(&mut self);
+}
+
+pub struct Tween<T: Lerp> {
+    pub from: T,
+    pub to: T,
+    pub duration: f32,
+    pub easing: Easing,
+    pub delay: f32,
+    pub repeat: Repeat,
+    pub yoyo: bool,
+    value: T,
+    elapsed: f32,
+    waited: f32,
+    cycles: u32,
+    finished: bool,
+    on_update: Option<Box<dyn FnMut(T)>>,
+    on_complete: Option<Box<dyn FnMut()>>,
+}
+
+impl<T: Lerp> Tween<T> {
+    // `duration` is in seconds.
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing: Easing::Linear,
+            delay: 0.0,
+            repeat: Repeat::Never,
+            yoyo: false,
+            value: from,
+            elapsed: 0.0,
+            waited: 0.0,
+            cycles: 0,
+            finished: false,
+            on_update: None,
+            on_complete: None,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    // Plays every other repetition backwards.
+    pub fn with_yoyo(mut self, yoyo: bool) -> Self {
+        self.yoyo = yoyo;
+        self
+    }
+
+    // Called with the new value every time the tween advances, e.g. to write into a sprite
+    // position or a `PixmapPaint` opacity held elsewhere.
+    pub fn on_update(mut self, f: impl FnMut(T) + 'static) -> Self {
+        self.on_update = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_complete(mut self, f: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(f));
+        self
+    }
+
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    // Linear progress through the current repetition.
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.elapsed / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    fn reversed(&self) -> bool {
+        self.yoyo && self.cycles % 2 == 1
+    }
+
+    fn set_progress(&mut self, t: f32) {
+        let t = if self.reversed() { 1.0 - t } else { t };
+        self.value = self.from.lerp(self.to, self.easing.apply(t));
+        if let Some(f) = &mut self.on_update {
+            f(self.value);
+        }
+    }
+
+    fn is_last_cycle(&self) -> bool {
+        match self.repeat {
+            Repeat::Never => true,
+            Repeat::Times(n) => self.cycles >= n,
+            Repeat::Forever => false,
+        }
+    }
+}
+
+impl<T: Lerp> Animation for Tween<T> {
+    fn advance(&mut self, dt: f32) -> f32 {
+        if self.finished {
+            return dt;
+        }
+        let mut dt = dt;
+        if self.waited < self.delay {
+            let wait = dt.min(self.delay - self.waited);
+            self.waited += wait;
+            dt -= wait;
+            if self.waited < self.delay {
+                return 0.0;
+            }
+        }
+
+        self.elapsed += dt;
+        while self.elapsed >= self.duration {
+            if self.is_last_cycle() {
+                let leftover = self.elapsed - self.duration;
+                self.elapsed = self.duration;
+                self.set_progress(1.0);
+                self.finished = true;
+                if let Some(f) = &mut self.on_complete {
+                    f();
+                }
+                return leftover;
+            }
+            self.elapsed -= self.duration;
+            self.cycles += 1;
+            if self.duration <= 0.0 {
+                // a zero length tween repeating forever would never leave this loop
+                break;
+            }
+        }
+        self.set_progress(self.progress());
+        0.0
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn reset(&mut self) {
+        self.value = self.from;
+        self.elapsed = 0.0;
+        self.waited = 0.0;
+        self.cycles = 0;
+        self.finished = false;
+    }
+}
+
+pub struct Delay {
+    pub duration: f32,
+    elapsed: f32,
+}
+
+impl Delay {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Animation for Delay {
+    fn advance(&mut self, dt: f32) -> f32 {
+        self.elapsed += dt;
+        (self.elapsed - self.duration).max(0.0).min(dt)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+}
+
+// Plays its animations one after another.
+#[derive(Default)]
+pub struct Sequence {
+    items: Vec<Box<dyn Animation>>,
+    index: usize,
+    pub repeat: Repeat,
+    cycles: u32,
+    on_complete: Option<Box<dyn FnMut()>>,
+}
+
+impl Sequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn then(mut self, animation: impl Animation + 'static) -> Self {
+        self.items.push(Box::new(animation));
+        self
+    }
+
+    pub fn wait(self, duration: f32) -> Self {
+        self.then(Delay::new(duration))
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn on_complete(mut self, f: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(f));
+        self
+    }
+}
+
+impl Animation for Sequence {
+    fn advance(&mut self, dt: f32) -> f32 {
+        if self.is_finished() {
+            return dt;
+        }
+        let mut dt = dt;
+        // `dt` when the sequence last started over within this call
+        let mut restarted_at = None;
+        loop {
+            let Some(item) = self.items.get_mut(self.index) else {
+                let last_cycle = match self.repeat {
+                    Repeat::Never => true,
+                    Repeat::Times(n) => self.cycles >= n,
+                    Repeat::Forever => false,
+                };
+                if last_cycle || self.items.is_empty() {
+                    if let Some(f) = &mut self.on_complete {
+                        f();
+                    }
+                    return dt;
+                }
+                if restarted_at == Some(dt) {
+                    // a whole cycle went by without using any time, so repeating forever would
+                    // never leave this loop
+                    return 0.0;
+                }
+                restarted_at = Some(dt);
+                self.cycles += 1;
+                self.index = 0;
+                self.items.iter_mut().for_each(|item| item.reset());
+                continue;
+            };
+            dt = item.advance(dt);
+            if !item.is_finished() {
+                return 0.0;
+            }
+            self.index += 1;
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.index >= self.items.len()
+            && match self.repeat {
+                Repeat::Never => true,
+                Repeat::Times(n) => self.cycles >= n,
+                Repeat::Forever => false,
+            }
+    }
+
+    fn reset(&mut self) {
+        self.index = 0;
+        self.cycles = 0;
+        self.items.iter_mut().for_each(|item| item.reset());
+    }
+}
+
+// Plays its animations at the same time and finishes when the longest one does.
+#[derive(Default)]
+pub struct Parallel {
+    items: Vec<Box<dyn Animation>>,
+    done: bool,
+    on_complete: Option<Box<dyn FnMut()>>,
+}
+
+impl Parallel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, animation: impl Animation + 'static) -> Self {
+        self.items.push(Box::new(animation));
+        self
+    }
+
+    pub fn on_complete(mut self, f: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(f));
+        self
+    }
+}
+
+impl Animation for Parallel {
+    fn advance(&mut self, dt: f32) -> f32 {
+        if self.done {
+            return dt;
+        }
+        let leftover = self
+            .items
+            .iter_mut()
+            .map(|item| item.advance(dt))
+            .fold(dt, f32::min);
+        if self.items.iter().all(|item| item.is_finished()) {
+            self.done = true;
+            if let Some(f) = &mut self.on_complete {
+                f();
+            }
+            return leftover;
+        }
+        0.0
+    }
+
+    fn is_finished(&self) -> bool {
+        self.done
+    }
+
+    fn reset(&mut self) {
+        self.done = false;
+        self.items.iter_mut().for_each(|item| item.reset());
+    }
+}
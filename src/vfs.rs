@@ -0,0 +1,379 @@
+use crate::{Error, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Cursor, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, RwLock, RwLockReadGuard},
+};
+use zip::ZipArchive;
+
+// Something mounted into a `Vfs`. Paths are relative to the mount point, '/' separated and
+// already cleaned of `.`, `..` and empty components.
+pub trait Mount: Send + Sync {
+    // `io::ErrorKind::NotFound` if the file isn't there, so the next mount gets a look.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+    fn exists(&self, path: &str) -> bool;
+    // Names of the files and directories in `dir`, with a trailing '/' on directories.
+    fn list(&self, dir: &str) -> Vec<String>;
+    // Where the file lives on disk, if it does, for watching it.
+    fn real_path(&self, _path: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+// A directory on disk.
+pub struct Dir(pub PathBuf);
+
+impl Mount for Dir {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.0.join(path))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.0.join(path).exists()
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(self.0.join(dir)) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                Some(match entry.file_type().ok()?.is_dir() {
+                    true => name + "/",
+                    false => name,
+                })
+            })
+            .collect()
+    }
+
+    fn real_path(&self, path: &str) -> Option<PathBuf> {
+        let path = self.0.join(path);
+        path.exists().then_some(path)
+    }
+}
+
+// A zip archive, read fully into memory when it's mounted.
+pub struct Zip {
+    archive: Mutex<ZipArchive<Cursor<Vec<u8>>>>,
+    names: BTreeSet<String>,
+}
+
+impl Zip {
+    pub fn new(data: Vec<u8>) -> zip::result::ZipResult<Self> {
+        let archive = ZipArchive::new(Cursor::new(data))?;
+        let names = archive
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(str::to_owned)
+            .collect();
+        Ok(Self {
+            archive: Mutex::new(archive),
+            names,
+        })
+    }
+}
+
+impl Mount for Zip {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        if !self.names.contains(path) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        let mut archive = self.archive.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = archive.by_name(path)?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.names.contains(path)
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        list(self.names.iter().map(String::as_str), dir)
+    }
+}
+
+// Files compiled into the binary, e.g. `("card.png", include_bytes!("../assets/card.png"))`.
+#[derive(Default)]
+pub struct Embedded(pub BTreeMap<String, &'static [u8]>);
+
+impl Embedded {
+    pub fn new<'a>(files: impl IntoIterator<Item = (&'a str, &'static [u8])>) -> Self {
+        Self(
+            files
+                .into_iter()
+                .filter_map(|(name, data)| Some((clean(name).ok()?, data)))
+                .collect(),
+        )
+    }
+}
+
+impl Mount for Embedded {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.0
+            .get(path)
+            .map(|data| data.to_vec())
+            .ok_or(io::ErrorKind::NotFound.into())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.0.contains_key(path)
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        list(self.0.keys().map(String::as_str), dir)
+    }
+}
+
+// Lists the entries of `dir` from a flat set of file paths.
+fn list<'a>(names: impl Iterator<Item = &'a str>, dir: &str) -> Vec<String> {
+    let prefix = match dir {
+        "" => String::new(),
+        dir => format!("{}/", dir),
+    };
+    let entries: BTreeSet<String> = names
+        .filter_map(|name| name.strip_prefix(&prefix))
+        .map(|rest| match rest.split_once('/') {
+            Some((dir, _)) => format!("{}/", dir),
+            None => rest.to_owned(),
+        })
+        .collect();
+    entries.into_iter().collect()
+}
+
+// Normalises a virtual path, refusing to climb out of a mount with `..`.
+fn clean(path: &str) -> Result<String> {
+    let mut parts = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                return Err(Error::Io {
+                    path: path.into(),
+                    source: io::Error::new(io::ErrorKind::InvalidInput, "path leaves its mount"),
+                })
+            }
+            part => parts.push(part),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+struct MountPoint {
+    point: String,
+    priority: i32,
+    mount: Box<dyn Mount>,
+}
+
+impl MountPoint {
+    // The path relative to this mount, if it falls under it.
+    fn relative<'p>(&self, path: &'p str) -> Option<&'p str> {
+        if self.point.is_empty() {
+            return Some(path);
+        }
+        match path.strip_prefix(&self.point)? {
+            "" => Some(""),
+            rest => rest.strip_prefix('/'),
+        }
+    }
+}
+
+// Directories, archives and embedded files mounted under virtual paths, which every corsola loader
+// reads through. Lookups go from the highest priority mount down, and between mounts of the same
+// priority the one mounted last wins, so a mod mounted over the base game replaces just the files
+// it has. Absolute paths skip the mounts and read straight from disk.
+//
+// The global instance starts with the working directory mounted at the root, at the lowest
+// priority, so relative paths behave as they would without a `Vfs`.
+#[derive(Clone, Default)]
+pub struct Vfs {
+    mounts: Arc<RwLock<Vec<MountPoint>>>,
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn global() -> Self {
+        static GLOBAL: OnceLock<Vfs> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| {
+                let vfs = Vfs::new();
+                vfs.mount_ex("", i32::MIN, Dir(".".into()));
+                vfs
+            })
+            .clone()
+    }
+
+    fn mounts(&self) -> RwLockReadGuard<'_, Vec<MountPoint>> {
+        self.mounts.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn mount(&self, point: &str, mount: impl Mount + 'static) {
+        self.mount_ex(point, 0, mount);
+    }
+
+    pub fn mount_ex(&self, point: &str, priority: i32, mount: impl Mount + 'static) {
+        // a bad mount point just can't be reached, the same as a path that isn't there
+        let point = clean(point).unwrap_or_else(|_| point.to_owned());
+        let mut mounts = self.mounts.write().unwrap_or_else(|e| e.into_inner());
+        let index = mounts
+            .iter()
+            .position(|m| m.priority <= priority)
+            .unwrap_or(mounts.len());
+        mounts.insert(
+            index,
+            MountPoint {
+                point,
+                priority,
+                mount: Box::new(mount),
+            },
+        );
+    }
+
+    pub fn mount_dir(&self, point: &str, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(Error::Io {
+                path: dir.to_path_buf(),
+                source: io::ErrorKind::NotFound.into(),
+            });
+        }
+        self.mount(point, Dir(dir.to_path_buf()));
+        Ok(())
+    }
+
+    // The archive itself is read through the `Vfs`, so it can be embedded or inside another one.
+    pub fn mount_zip(&self, point: &str, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let zip = Zip::new(self.read(path)?).map_err(|source| Error::Archive {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        self.mount(point, zip);
+        Ok(())
+    }
+
+    pub fn mount_embedded<'a>(
+        &self,
+        point: &str,
+        files: impl IntoIterator<Item = (&'a str, &'static [u8])>,
+    ) {
+        self.mount(point, Embedded::new(files));
+    }
+
+    // Removes everything mounted at `point`, returning whether there was anything.
+    pub fn unmount(&self, point: &str) -> bool {
+        let Ok(point) = clean(point) else {
+            return false;
+        };
+        let mut mounts = self.mounts.write().unwrap_or_else(|e| e.into_inner());
+        let before = mounts.len();
+        mounts.retain(|m| m.point != point);
+        mounts.len() != before
+    }
+
+    pub fn read(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        let io_error = |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if path.is_absolute() {
+            return fs::read(path).map_err(io_error);
+        }
+        let virt = clean(&path.to_string_lossy())?;
+        for m in self.mounts().iter() {
+            let Some(rel) = m.relative(&virt) else {
+                continue;
+            };
+            match m.mount.read(rel) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                result => return result.map_err(io_error),
+            }
+        }
+        Err(io_error(io::ErrorKind::NotFound.into()))
+    }
+
+    pub fn read_to_string(&self, path: impl AsRef<Path>) -> Result<String> {
+        let path = path.as_ref();
+        String::from_utf8(self.read(path)?).map_err(|e| Error::Io {
+            path: path.to_path_buf(),
+            source: io::Error::new(io::ErrorKind::InvalidData, e),
+        })
+    }
+
+    pub fn exists(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            return path.exists();
+        }
+        let Ok(virt) = clean(&path.to_string_lossy()) else {
+            return false;
+        };
+        self.mounts()
+            .iter()
+            .any(|m| m.relative(&virt).is_some_and(|rel| m.mount.exists(rel)))
+    }
+
+    // The entries of `dir` across every mount, sorted, with a trailing '/' on directories.
+    pub fn list(&self, dir: impl AsRef<Path>) -> Vec<String> {
+        let dir = dir.as_ref();
+        if dir.is_absolute() {
+            return Dir(dir.to_path_buf()).list("");
+        }
+        let Ok(virt) = clean(&dir.to_string_lossy()) else {
+            return Vec::new();
+        };
+        let mut entries = BTreeSet::new();
+        for m in self.mounts().iter() {
+            if let Some(rel) = m.relative(&virt) {
+                entries.extend(m.mount.list(rel));
+            } else if let Some(rest) = m.point.strip_prefix(&virt) {
+                // a mount point inside `dir` shows up as a directory
+                let rest = match virt.as_str() {
+                    "" => rest,
+                    _ => rest.strip_prefix('/').unwrap_or(""),
+                };
+                if let Some(first) = rest.split('/').find(|part| !part.is_empty()) {
+                    entries.insert(format!("{}/", first));
+                }
+            }
+        }
+        entries.into_iter().collect()
+    }
+
+    // Every file under `dir`, recursively, as paths that can be passed to `read`.
+    pub fn walk(&self, dir: impl AsRef<Path>) -> Vec<PathBuf> {
+        let dir = dir.as_ref();
+        let mut files = Vec::new();
+        for entry in self.list(dir) {
+            match entry.strip_suffix('/') {
+                Some(sub) => files.extend(self.walk(dir.join(sub))),
+                None => files.push(dir.join(entry)),
+            }
+        }
+        files
+    }
+
+    // The file on disk `path` resolves to, if it's in a mounted directory rather than an archive
+    // or embedded.
+    pub fn real_path(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            return path.exists().then(|| path.to_path_buf());
+        }
+        let virt = clean(&path.to_string_lossy()).ok()?;
+        let mounts = self.mounts();
+        let m = mounts
+            .iter()
+            .find(|m| m.relative(&virt).is_some_and(|rel| m.mount.exists(rel)))?;
+        m.mount.real_path(m.relative(&virt)?)
+    }
+}
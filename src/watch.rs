@@ -0,0 +1,233 @@
+use crate::{
+    audio::{Audio, SoundId},
+    font::is_font,
+    texture::{TextureId, Textures},
+    vfs::Vfs,
+    Error, FontContext, Result,
+};
+use kira::manager::backend::Backend;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::{HashMap, HashSet},
+    io, iter, mem,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, Instant},
+};
+
+// Editors and exporters often write a file in several steps, so a change is only reported once
+// the file has been quiet for this long.
+const SETTLE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Texture(TextureId),
+    Sound(SoundId, PathBuf),
+    // a font file, or a directory passed to `FontContext::load_font_dir`
+    Font(PathBuf),
+}
+
+// Reloads textures, sounds and fonts when their files change, for iterating on assets while the
+// game runs. Ids and font contexts stay valid, only what's behind them is swapped. Directories
+// are watched rather than files, since many editors save by replacing the file. Only assets in
+// mounted directories can be watched, not ones in archives or embedded in the binary.
+pub struct AssetWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    watched: HashSet<PathBuf>,
+    // keyed by canonical path
+    assets: HashMap<PathBuf, Change>,
+    font_dirs: Vec<PathBuf>,
+    // when each changed asset was last written to
+    pending: HashMap<PathBuf, Instant>,
+    // settled changes that haven't been taken by `poll` or a reload call yet
+    changed: Vec<Change>,
+}
+
+impl AssetWatcher {
+    pub fn new() -> Result<Self> {
+        let (tx, events) = channel();
+        Ok(Self {
+            watcher: notify::recommended_watcher(tx)?,
+            events,
+            watched: HashSet::new(),
+            assets: HashMap::new(),
+            font_dirs: Vec::new(),
+            pending: HashMap::new(),
+            changed: Vec::new(),
+        })
+    }
+
+    fn watch(&mut self, path: &Path, change: Change) -> Result<()> {
+        let path = canonicalize(path)?;
+        if let Some(dir) = path.parent() {
+            if !self.watched.contains(dir) {
+                self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                self.watched.insert(dir.to_owned());
+            }
+        }
+        self.assets.insert(path, change);
+        Ok(())
+    }
+
+    pub fn watch_texture(&mut self, textures: &Textures, texture: TextureId) -> Result<()> {
+        let path = textures
+            .path(texture)
+            .ok_or_else(|| Error::NoPath(format!("Texture {:?}", texture)))?;
+        self.watch(path, Change::Texture(texture))
+    }
+
+    // Watches every texture loaded from a file.
+    pub fn watch_textures(&mut self, textures: &Textures) -> Result<()> {
+        for (texture, _) in textures.iter() {
+            if let Some(path) = textures.path(texture) {
+                self.watch(path, Change::Texture(texture))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn watch_sound(&mut self, path: impl AsRef<Path>, sound: SoundId) -> Result<()> {
+        let path = path.as_ref();
+        self.watch(path, Change::Sound(sound, path.to_owned()))
+    }
+
+    // Watches the files and directories fonts have been loaded from so far.
+    pub fn watch_fonts(&mut self, fonts: &FontContext) -> Result<()> {
+        for path in fonts.font_paths() {
+            if !is_dir(&path) {
+                self.watch(&path, Change::Font(path.clone()))?;
+                continue;
+            }
+            let dir = canonicalize(&path)?;
+            // font directories are scanned recursively
+            self.watcher.watch(&dir, RecursiveMode::Recursive)?;
+            self.watched.insert(dir.clone());
+            self.font_dirs.push(dir);
+        }
+        Ok(())
+    }
+
+    fn key(&self, path: &Path) -> Option<PathBuf> {
+        if self.assets.contains_key(path) {
+            return Some(path.to_owned());
+        }
+        if !is_font(path) {
+            return None;
+        }
+        self.font_dirs
+            .iter()
+            .find(|dir| path.starts_with(dir))
+            .cloned()
+    }
+
+    fn settle(&mut self) {
+        for event in self.events.try_iter() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if matches!(event.kind, EventKind::Access(_) | EventKind::Remove(_)) {
+                continue;
+            }
+            for path in &event.paths {
+                if let Some(key) = self.key(path) {
+                    self.pending.insert(key, Instant::now());
+                }
+            }
+        }
+
+        let settled: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, written)| written.elapsed() >= SETTLE)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled {
+            self.pending.remove(&path);
+            let change = match self.assets.get(&path) {
+                Some(change) => change.clone(),
+                None => Change::Font(path),
+            };
+            if !self.changed.contains(&change) {
+                self.changed.push(change);
+            }
+        }
+    }
+
+    // Returns the assets whose files have changed and settled since the last call.
+    pub fn poll(&mut self) -> Vec<Change> {
+        self.settle();
+        mem::take(&mut self.changed)
+    }
+
+    // The reload calls each take only their own kind of change, the rest wait for their call or
+    // `poll`. An asset that fails to load, say because it's half written, keeps its previous
+    // version.
+    pub fn reload_textures(&mut self, textures: &mut Textures) -> Vec<(TextureId, Result<()>)> {
+        self.settle();
+        let mut reloaded = Vec::new();
+        self.changed.retain(|change| match change {
+            Change::Texture(texture) => {
+                reloaded.push((*texture, textures.reload(*texture)));
+                false
+            }
+            _ => true,
+        });
+        reloaded
+    }
+
+    pub fn reload_sounds<B: Backend>(
+        &mut self,
+        audio: &mut Audio<B>,
+    ) -> Vec<(SoundId, Result<()>)> {
+        self.settle();
+        let mut reloaded = Vec::new();
+        self.changed.retain(|change| match change {
+            Change::Sound(sound, path) => {
+                reloaded.push((*sound, audio.reload(*sound, path)));
+                false
+            }
+            _ => true,
+        });
+        reloaded
+    }
+
+    pub fn reload_fonts(&mut self, fonts: &FontContext) -> Vec<(PathBuf, Result<()>)> {
+        self.settle();
+        let mut reloaded = Vec::new();
+        self.changed.retain(|change| match change {
+            Change::Font(dir) if is_dir(dir) => {
+                fonts.load_fonts(iter::empty(), true);
+                reloaded.push((dir.clone(), Ok(())));
+                false
+            }
+            Change::Font(path) => {
+                reloaded.push((path.clone(), fonts.reload_font_file(path).map(|_| ())));
+                false
+            }
+            _ => true,
+        });
+        reloaded
+    }
+}
+
+// The file on disk behind a `Vfs` path.
+fn canonicalize(path: &Path) -> Result<PathBuf> {
+    let io_error = |source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let real = Vfs::global().real_path(path).ok_or_else(|| {
+        io_error(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "only files on disk can be watched",
+        ))
+    })?;
+    real.canonicalize().map_err(io_error)
+}
+
+fn is_dir(path: &Path) -> bool {
+    Vfs::global()
+        .real_path(path)
+        .is_some_and(|real| real.is_dir())
+}
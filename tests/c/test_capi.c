@@ -0,0 +1,131 @@
+/* Exercises the C API against the static library.
+ *
+ *   cargo build && make -C tests/c && tests/c/test_capi
+ *
+ * Set CORSOLA_HEADLESS=1 to skip the part that opens a window. `make -C tests/c check-header`
+ * checks that include/corsola.h matches src/capi.rs. */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "corsola.h"
+
+#define CHECK(call)                                                                    \
+  do {                                                                                 \
+    CorsolaResult result_ = (call);                                                    \
+    if (result_ != CORSOLA_RESULT_OK) {                                                \
+      fprintf(stderr, "%s:%d: %s failed (%d): %s\n", __FILE__, __LINE__, #call, result_, \
+              corsola_last_error());                                                   \
+      exit(1);                                                                         \
+    }                                                                                  \
+  } while (0)
+
+#define EXPECT(call, expected)                                                         \
+  do {                                                                                 \
+    CorsolaResult result_ = (call);                                                    \
+    if (result_ != (expected)) {                                                       \
+      fprintf(stderr, "%s:%d: %s returned %d, expected %s\n", __FILE__, __LINE__, #call, \
+              result_, #expected);                                                     \
+      exit(1);                                                                         \
+    }                                                                                  \
+  } while (0)
+
+#define FRAMES 30
+
+typedef struct {
+  CorsolaSurface *surface;
+  CorsolaPixmap *sprite;
+  int frames;
+} State;
+
+static void resumed(CorsolaApp *app, void *user_data) {
+  State *state = user_data;
+  /* surfaces are reattached automatically after a suspend */
+  if (state->surface) {
+    return;
+  }
+  CHECK(corsola_surface_new(app, "corsola C API", 640.0, 480.0, &state->surface));
+  EXPECT(corsola_surface_load_font(state->surface, "does/not/exist.ttf"), CORSOLA_RESULT_IO);
+
+  uint32_t width, height;
+  CHECK(corsola_surface_size(state->surface, &width, &height));
+  if (width == 0 || height == 0) {
+    fprintf(stderr, "surface has no size\n");
+    exit(1);
+  }
+  CHECK(corsola_surface_request_redraw(state->surface));
+}
+
+static void redraw(CorsolaApp *app, CorsolaSurface *surface, void *user_data) {
+  State *state = user_data;
+  CHECK(corsola_surface_fill(surface, 20, 20, 60, 255));
+  CHECK(corsola_surface_blit(surface, state->sprite, state->frames * 4, 100, 1.0f));
+  CHECK(corsola_surface_text(surface, "Hello from C", 20.0f, 20.0f, 40.0f, 255, 255, 255, 255));
+  CHECK(corsola_surface_update(surface));
+
+  if (++state->frames >= FRAMES) {
+    corsola_surface_free(surface);
+    state->surface = NULL;
+    corsola_app_exit(app);
+  } else {
+    CHECK(corsola_surface_request_redraw(surface));
+  }
+}
+
+static void close_requested(CorsolaApp *app, CorsolaSurface *surface, void *user_data) {
+  (void)surface;
+  (void)user_data;
+  corsola_app_exit(app);
+}
+
+int main(int argc, char **argv) {
+  const char *png = argc > 1 ? argv[1] : "examples/undermine_cloth.png";
+  State state = {0};
+  CorsolaPixmap *pixmap = NULL;
+  uint32_t width, height;
+
+  EXPECT(corsola_pixmap_new(0, 0, &pixmap), CORSOLA_RESULT_INVALID_ARGUMENT);
+  if (corsola_last_error() == NULL || strlen(corsola_last_error()) == 0) {
+    fprintf(stderr, "no error message set\n");
+    return 1;
+  }
+  EXPECT(corsola_pixmap_new(16, 16, NULL), CORSOLA_RESULT_NULL_POINTER);
+  EXPECT(corsola_pixmap_size(NULL, &width, &height), CORSOLA_RESULT_NULL_POINTER);
+  EXPECT(corsola_pixmap_load_png("does/not/exist.png", &pixmap), CORSOLA_RESULT_IO);
+  EXPECT(corsola_pixmap_load_png(NULL, &pixmap), CORSOLA_RESULT_NULL_POINTER);
+  EXPECT(corsola_surface_update(NULL), CORSOLA_RESULT_NULL_POINTER);
+
+  CHECK(corsola_pixmap_new(32, 16, &pixmap));
+  CHECK(corsola_pixmap_size(pixmap, &width, &height));
+  if (width != 32 || height != 16) {
+    fprintf(stderr, "pixmap is %ux%u, expected 32x16\n", width, height);
+    return 1;
+  }
+  corsola_pixmap_free(pixmap);
+
+  CHECK(corsola_pixmap_load_png(png, &state.sprite));
+  CHECK(corsola_pixmap_size(state.sprite, &width, &height));
+  printf("loaded %s (%ux%u)\n", png, width, height);
+
+  if (getenv("CORSOLA_HEADLESS") == NULL) {
+    CorsolaEventLoop *event_loop = NULL;
+    CorsolaCallbacks callbacks = {
+        .user_data = &state,
+        .resumed = resumed,
+        .suspended = NULL,
+        .redraw = redraw,
+        .close_requested = close_requested,
+    };
+    EXPECT(corsola_event_loop_run(NULL, &callbacks), CORSOLA_RESULT_NULL_POINTER);
+    CHECK(corsola_event_loop_new(&event_loop));
+    CHECK(corsola_event_loop_run(event_loop, &callbacks));
+    if (state.frames < FRAMES) {
+      fprintf(stderr, "only drew %d frames\n", state.frames);
+      return 1;
+    }
+  }
+
+  corsola_pixmap_free(state.sprite);
+  printf("ok\n");
+  return 0;
+}